@@ -16,11 +16,13 @@ fn main() {
         for entry in fs::read_dir(examples_dir).unwrap() {
             let entry = entry.unwrap();
             let path = entry.path();
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("ntq") {
+            // .ntq は独自形式、.txt は青空文庫形式の問題ファイルとして扱う
+            let extension = path.extension().and_then(|s| s.to_str());
+            if path.is_file() && matches!(extension, Some("ntq") | Some("txt")) {
                 if let Some(file_stem) = path.file_stem().and_then(|s| s.to_str()) {
                     // プロジェクトルートからの相対パスを保存
                     let relative_path = path.strip_prefix(&manifest_dir).unwrap().to_str().unwrap().replace('\\', "/");
-                    problem_files.push((file_stem.to_string(), relative_path.to_string()));
+                    problem_files.push((file_stem.to_string(), relative_path.to_string(), extension.unwrap().to_string()));
                 }
             }
         }
@@ -31,15 +33,22 @@ fn main() {
 
     // 問題ファイルの名前の静的配列を生成
     writeln!(f, "pub const PROBLEM_FILES_NAMES: &[&str] = &[").unwrap();
-    for (name, _) in &problem_files {
+    for (name, _, _) in &problem_files {
         writeln!(f, "    \"{}\",", name).unwrap();
     }
     writeln!(f, "];\n").unwrap();
 
+    // 問題ファイルの形式（拡張子）の静的配列を生成
+    writeln!(f, "pub const PROBLEM_FILES_EXTENSIONS: &[&str] = &[").unwrap();
+    for (_, _, extension) in &problem_files {
+        writeln!(f, "    \"{}\",", extension).unwrap();
+    }
+    writeln!(f, "];\n").unwrap();
+
     // 問題ファイルの内容を動的に取得するための関数を生成
     writeln!(f, "pub fn get_problem_content(index: usize) -> &'static str {{").unwrap();
     writeln!(f, "    match index {{").unwrap();
-    for (i, (_, path)) in problem_files.iter().enumerate() {
+    for (i, (_, path, _)) in problem_files.iter().enumerate() {
         // include_str! にはプロジェクトルートからの相対パスを渡す
         writeln!(f, "        {} => include_str!(concat!(env!(\"CARGO_MANIFEST_DIR\"), \"/{}\")),", i, path).unwrap();
     }
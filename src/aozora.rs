@@ -0,0 +1,373 @@
+// src/aozora.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::{string::{String, ToString}, vec::Vec};
+#[cfg(not(feature = "uefi"))]
+use std::string::{String, ToString};
+#[cfg(not(feature = "uefi"))]
+use std::vec::Vec;
+
+use crate::model::{Content, Line, Segment, Word};
+
+/// ルビの親文字の開始位置を明示する記号
+const RUBY_BASE_MARK: char = '｜';
+/// 文の終わりとして扱う文字
+const SENTENCE_TERMINATORS: [char; 5] = ['。', '！', '？', '!', '?'];
+/// 文末記号の直後にあれば同じ文に含める閉じ括弧
+const CLOSING_BRACKETS: [char; 6] = ['」', '』', '）', '】', '〉', '》'];
+/// 単語の区切りとして扱う読点
+const WORD_BREAKS: [char; 2] = ['、', '，'];
+
+/// 青空文庫の本文で使われる文字の種類
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CharClass {
+    Kanji,
+    Hiragana,
+    Katakana,
+    Other,
+}
+
+fn char_class(c: char) -> CharClass {
+    match c {
+        '\u{4E00}'..='\u{9FFF}' | '\u{3400}'..='\u{4DBF}' | '\u{F900}'..='\u{FAFF}' | '々' | '〆' | '〇' | 'ヶ' => CharClass::Kanji,
+        '\u{3041}'..='\u{309F}' => CharClass::Hiragana,
+        '\u{30A0}'..='\u{30FF}' => CharClass::Katakana,
+        _ => CharClass::Other,
+    }
+}
+
+/// 本文1行を、ルビ注記と入力注記を解釈したセグメント列に変換する
+///
+/// `｜親文字《よみ》` は `｜` から `《` までを、`漢字《よみ》` は直前の漢字の連続を親文字とする。
+/// `［＃…］` の入力注記と、外字注記の目印である `※` は取り除く。
+fn parse_ruby_line(line: &str) -> Vec<Segment> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut segments = Vec::new();
+    let mut plain: Vec<char> = Vec::new();
+    let mut base_start: Option<usize> = None;
+    let mut pos = 0;
+
+    let flush_plain = |plain: &mut Vec<char>, segments: &mut Vec<Segment>| {
+        if !plain.is_empty() {
+            segments.push(Segment::Plain { text: plain.drain(..).collect() });
+        }
+    };
+
+    while pos < chars.len() {
+        match chars[pos] {
+            RUBY_BASE_MARK => {
+                base_start = Some(plain.len());
+            }
+            '※' if chars.get(pos + 1) == Some(&'［') && chars.get(pos + 2) == Some(&'＃') => {}
+            '［' if chars.get(pos + 1) == Some(&'＃') => {
+                // 入力注記は閉じ括弧まで読み飛ばす
+                while pos < chars.len() && chars[pos] != '］' {
+                    pos += 1;
+                }
+            }
+            '《' => {
+                let mut reading = String::new();
+                pos += 1;
+                while pos < chars.len() && chars[pos] != '》' {
+                    reading.push(chars[pos]);
+                    pos += 1;
+                }
+
+                // 親文字の開始位置を決める。明示がなければ直前の同じ字種の連続を親文字とする
+                let start = base_start.take().unwrap_or_else(|| {
+                    let class = plain.last().map(|&c| char_class(c));
+                    let mut start = plain.len();
+                    while start > 0 && Some(char_class(plain[start - 1])) == class {
+                        start -= 1;
+                    }
+                    start
+                });
+                let base: String = plain.drain(start..).collect();
+                flush_plain(&mut plain, &mut segments);
+                segments.push(Segment::Annotated { base, reading });
+            }
+            c => plain.push(c),
+        }
+        pos += 1;
+    }
+    flush_plain(&mut plain, &mut segments);
+
+    segments
+}
+
+/// セグメント列を文末記号で区切り、文ごとのセグメント列に分割する
+pub(crate) fn split_sentences(segments: Vec<Segment>) -> Vec<Vec<Segment>> {
+    let mut sentences = Vec::new();
+    let mut current = Vec::new();
+
+    for segment in segments {
+        let text = match segment {
+            Segment::Annotated { .. } => {
+                current.push(segment);
+                continue;
+            }
+            Segment::Plain { text } => text,
+        };
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut buffer = String::new();
+        let mut i = 0;
+        while i < chars.len() {
+            buffer.push(chars[i]);
+            if SENTENCE_TERMINATORS.contains(&chars[i]) {
+                // 連続する文末記号や閉じ括弧は同じ文に含める
+                while i + 1 < chars.len() && (SENTENCE_TERMINATORS.contains(&chars[i + 1]) || CLOSING_BRACKETS.contains(&chars[i + 1])) {
+                    i += 1;
+                    buffer.push(chars[i]);
+                }
+                current.push(Segment::Plain { text: core::mem::take(&mut buffer) });
+                sentences.push(core::mem::take(&mut current));
+            }
+            i += 1;
+        }
+        if !buffer.is_empty() {
+            current.push(Segment::Plain { text: buffer });
+        }
+    }
+    if !current.is_empty() {
+        sentences.push(current);
+    }
+
+    sentences
+}
+
+/// 1文のセグメント列を単語にまとめる
+///
+/// ルビ付きのセグメントは直後の平仮名（送り仮名）と読点までを合わせて1単語とし、
+/// それ以外の地の文は読点の直後で単語を区切る。
+pub(crate) fn group_into_words(segments: Vec<Segment>) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut current: Vec<Segment> = Vec::new();
+
+    let finalize = |current: &mut Vec<Segment>, words: &mut Vec<Word>| {
+        if !current.is_empty() {
+            words.push(Word { segments: core::mem::take(current) });
+        }
+    };
+
+    for segment in segments {
+        match segment {
+            Segment::Annotated { .. } => {
+                finalize(&mut current, &mut words);
+                current.push(segment);
+            }
+            Segment::Plain { text } => {
+                let mut rest = text.as_str();
+
+                // 直前がルビ付きなら、続く平仮名を送り仮名として同じ単語に含める
+                if matches!(current.last(), Some(Segment::Annotated { .. })) {
+                    let okurigana_len = rest
+                        .char_indices()
+                        .find(|&(_, c)| char_class(c) != CharClass::Hiragana)
+                        .map_or(rest.len(), |(i, _)| i);
+                    // 送り仮名の直後の読点も同じ単語に含める
+                    let break_len = rest[okurigana_len..]
+                        .chars()
+                        .next()
+                        .filter(|c| WORD_BREAKS.contains(c))
+                        .map_or(0, char::len_utf8);
+                    let attached_len = okurigana_len + break_len;
+                    if attached_len > 0 {
+                        current.push(Segment::Plain { text: rest[..attached_len].to_string() });
+                        rest = &rest[attached_len..];
+                    }
+                    if !rest.is_empty() || break_len > 0 {
+                        finalize(&mut current, &mut words);
+                    }
+                }
+
+                let mut buffer = String::new();
+                for c in rest.chars() {
+                    buffer.push(c);
+                    if WORD_BREAKS.contains(&c) {
+                        current.push(Segment::Plain { text: core::mem::take(&mut buffer) });
+                        finalize(&mut current, &mut words);
+                    }
+                }
+                if !buffer.is_empty() {
+                    current.push(Segment::Plain { text: buffer });
+                }
+            }
+        }
+    }
+    finalize(&mut current, &mut words);
+
+    words
+}
+
+/// ヘッダ部分の記号説明ブロックの区切り線か
+fn is_separator_line(line: &str) -> bool {
+    line.len() >= 10 && line.chars().all(|c| c == '-')
+}
+
+/// 青空文庫形式のテキストを読み込み、`Content` に変換する
+///
+/// 1行目を題名とし、最初の空行までのヘッダ（著者名など）と、`-----` で囲まれた
+/// 記号説明のブロックを読み飛ばす。`底本：` で始まる行以降の奥付は取り込まない。
+/// 本文は文末記号ごとに1行へ分割する。
+///
+/// ルビが振られていない漢字には読みが付かないため、そうした行はそのままでは入力できない。
+/// `lines_without_reading` で見つけ、`drop_lines_without_reading` で除く。
+pub fn parse_aozora(input: &str) -> Content {
+    // 先頭のBOMは題名に含めない
+    let mut lines_iter = input.trim_start_matches('\u{FEFF}').lines();
+
+    // 題名
+    let title_segments = lines_iter.next().map(|line| parse_ruby_line(line.trim())).unwrap_or_default();
+    let title = Line { words: group_into_words(title_segments), split: None };
+
+    // 最初の空行までをヘッダとして読み飛ばす
+    for line in lines_iter.by_ref() {
+        if line.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut in_notes_block = false;
+    let mut is_first_body_line = true;
+    for line in lines_iter {
+        // 記号説明のブロック
+        if is_separator_line(line.trim()) && (in_notes_block || is_first_body_line) {
+            in_notes_block = !in_notes_block;
+            is_first_body_line = false;
+            continue;
+        }
+        if in_notes_block {
+            continue;
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        is_first_body_line = false;
+
+        // 奥付
+        if line.starts_with("底本：") {
+            break;
+        }
+
+        // 字下げの全角空白は取り除く
+        let body = line.trim_start_matches(['　', ' ']).trim_end();
+        for sentence in split_sentences(parse_ruby_line(body)) {
+            let words = group_into_words(sentence);
+            if !words.is_empty() {
+                lines.push(Line { words, split: None });
+            }
+        }
+    }
+
+    Content { title, lines, options: Default::default() }
+}
+
+/// 読みの付いていない漢字を含む行か。`ヶ` はかなとして入力できるため除く
+fn has_kanji_without_reading(line: &Line) -> bool {
+    line.words.iter().flat_map(|word| &word.segments).any(|segment| match segment {
+        Segment::Plain { text } => text.chars().any(|c| c != 'ヶ' && char_class(c) == CharClass::Kanji),
+        Segment::Annotated { .. } => false,
+    })
+}
+
+/// 読みの付いていない漢字を含み、入力できない行の番号（0から数える）
+pub fn lines_without_reading(content: &Content) -> Vec<usize> {
+    content.lines.iter().enumerate().filter(|(_, line)| has_kanji_without_reading(line)).map(|(index, _)| index).collect()
+}
+
+/// 読みの付いていない漢字を含む行を除く
+pub fn drop_lines_without_reading(content: &mut Content) {
+    content.lines.retain(|line| !has_kanji_without_reading(line));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain(text: &str) -> Segment {
+        Segment::Plain { text: text.to_string() }
+    }
+
+    fn annotated(base: &str, reading: &str) -> Segment {
+        Segment::Annotated { base: base.to_string(), reading: reading.to_string() }
+    }
+
+    #[test]
+    fn test_implicit_ruby_base() {
+        // 《》の直前の漢字の連続が親文字になることを確認
+        assert_eq!(
+            parse_ruby_line("ある日の事でございます。御釈迦様《おしゃかさま》は"),
+            vec![plain("ある日の事でございます。"), annotated("御釈迦様", "おしゃかさま"), plain("は")]
+        );
+    }
+
+    #[test]
+    fn test_explicit_ruby_base() {
+        // ｜で親文字の開始位置を指定するケース
+        assert_eq!(
+            parse_ruby_line("極楽の｜蓮池《はすいけ》のふち"),
+            vec![plain("極楽の"), annotated("蓮池", "はすいけ"), plain("のふち")]
+        );
+        // 平仮名を含む親文字も指定できる
+        assert_eq!(
+            parse_ruby_line("｜お釈迦《しゃか》様"),
+            vec![annotated("お釈迦", "しゃか"), plain("様")]
+        );
+    }
+
+    #[test]
+    fn test_strip_annotations() {
+        // 入力注記と外字注記が取り除かれることを確認
+        assert_eq!(
+            parse_ruby_line("［＃ここから２字下げ］蜘蛛の糸※［＃「特」、第3水準1-1-1］です"),
+            vec![plain("蜘蛛の糸です")]
+        );
+    }
+
+    #[test]
+    fn test_sentence_split_and_words() {
+        // 文末記号で行が分割され、送り仮名と読点で単語が区切られることを確認
+        let content = parse_aozora("題名\n著者\n\n「蜘蛛《くも》が出た。」と言い、逃《に》げた。池《いけ》のふちを、歩く。\n");
+        assert_eq!(content.lines.len(), 3);
+        assert_eq!(content.lines[0].words, vec![
+            Word { segments: vec![plain("「")] },
+            Word { segments: vec![annotated("蜘蛛", "くも"), plain("が")] },
+            Word { segments: vec![plain("出た。」")] },
+        ]);
+        assert_eq!(content.lines[1].words, vec![
+            Word { segments: vec![plain("と言い、")] },
+            Word { segments: vec![annotated("逃", "に"), plain("げた")] },
+            Word { segments: vec![plain("。")] },
+        ]);
+        assert_eq!(content.lines[2].words, vec![
+            Word { segments: vec![annotated("池", "いけ"), plain("のふちを、")] },
+            Word { segments: vec![plain("歩く。")] },
+        ]);
+    }
+
+    #[test]
+    fn test_header_and_footer() {
+        // ヘッダ、記号説明、奥付が本文から除かれることを確認
+        let text = "蜘蛛《くも》の糸\n芥川龍之介\n\n-------------------------------------------------------\n【テキスト中に現れる記号について】\n《》：ルビ\n-------------------------------------------------------\n\n　本文です。\n\n底本：「蜘蛛の糸・杜子春」新潮文庫\n";
+        let content = parse_aozora(text);
+        assert_eq!(content.title.to_string(), "蜘蛛の糸");
+        assert_eq!(content.lines.len(), 1);
+        assert_eq!(content.lines[0].to_string(), "本文です。");
+    }
+
+    #[test]
+    fn test_lines_without_reading() {
+        // ルビのない漢字を含む行だけが見つかり、除かれることを確認
+        let text = "題\n\n蜘蛛《くも》がいる。彼は言った。ひらがなとカタカナ。ヶ。\n";
+        let mut content = parse_aozora(text);
+        assert_eq!(content.lines.len(), 4);
+        assert_eq!(lines_without_reading(&content), vec![1]);
+        drop_lines_without_reading(&mut content);
+        assert_eq!(content.lines.iter().map(|line| line.to_string()).collect::<Vec<_>>(), ["蜘蛛がいる。", "ひらがなとカタカナ。", "ヶ。"]);
+    }
+}
@@ -9,6 +9,7 @@ extern crate alloc;
 use alloc::{
     format,
     string::{String, ToString},
+    vec,
     vec::Vec,
 };
 #[cfg(not(feature = "uefi"))]
@@ -17,8 +18,18 @@ use std::{
     vec::Vec,
 };
 
-use crate::model::{Model, ResultModel, Scroll, TypingModel, TypingStatus};
+use crate::aozora;
+use crate::drill::{self, KanaStats, WordIndex};
+use crate::endless::{self, EndlessStream};
+use crate::karuta::{self, KarutaSession};
+use crate::history::{self, HistoryEntry};
+use crate::html;
+use crate::layout_file::LayoutFile;
+use crate::model::{Content, ErrorPolicy, InputMode, Layout, LayoutPreset, Model, ResultModel, Scroll, SessionOptions, SpellingPreferences, SpellingStats, TimeLimit, TypingMetrics, TypingModel, TypingStatus};
 use crate::parser;
+use crate::review::{self, ReviewQueue};
+use crate::random;
+use crate::storage;
 use crate::typing;
 use crate::ui; // typing_rendererの代わりにuiをインポート
 use crate::renderer::gui_renderer;
@@ -27,6 +38,45 @@ use ab_glyph::FontRef;
 // ビルドスクリプトによってOUT_DIRに生成されたファイルを取り込む
 include!(concat!(env!("OUT_DIR"), "/problem_files.rs"));
 
+/// 問題ファイルを読み込み、ファイル形式に応じたパーサーで `Content` に変換する
+pub fn load_problem(index: usize) -> Content {
+    let problem_text = get_problem_content(index);
+    match PROBLEM_FILES_EXTENSIONS.get(index).copied() {
+        // ルビのない漢字を含む行は入力できないため除く
+        Some("txt") => {
+            let mut content = aozora::parse_aozora(problem_text);
+            aozora::drop_lines_without_reading(&mut content);
+            content
+        }
+        _ => parser::parse_problem(problem_text),
+    }
+}
+
+/// キー配列ファイルを探すディレクトリ（作業ディレクトリからの相対パス）
+#[cfg(not(any(feature = "uefi", target_arch = "wasm32")))]
+pub const LAYOUT_DIR: &str = "layouts";
+
+/// `LAYOUT_DIR` にあるキー配列ファイルをファイル名の順に読み込む。ディレクトリがなければ空を返す
+#[cfg(not(any(feature = "uefi", target_arch = "wasm32")))]
+pub fn load_layout_files() -> Vec<LayoutFile> {
+    let Ok(entries) = std::fs::read_dir(LAYOUT_DIR) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == crate::layout_file::LAYOUT_FILE_EXTENSION))
+        .collect();
+    paths.sort();
+    paths
+        .iter()
+        .filter_map(|path| {
+            let text = std::fs::read_to_string(path).ok()?;
+            let stem = path.file_stem()?.to_string_lossy();
+            Some(crate::layout_file::parse_layout_file(&text, &stem))
+        })
+        .collect()
+}
+
 /// アプリケーションの現在の状態（シーン）を定義するenum
 #[derive(PartialEq, Clone, Copy)]
 pub enum AppState {
@@ -35,6 +85,8 @@ pub enum AppState {
     Typing,
     Result,
     Settings, // 設定画面の状態を追加
+    Paused, // タイピング中のセッションを一時停止している状態
+    Analysis, // 直前のセッションの行ごとの詳しい分析を表示している状態
 }
 
 /// TUIの描画モードを定義するenum
@@ -52,6 +104,97 @@ pub enum FontChoice {
     NotoSerifJP,
 }
 
+/// 設定画面の項目
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SettingsItem {
+    Font(FontChoice),
+    Layout(LayoutPreset),
+    CustomLayout(usize), // `App::custom_layouts` の番号
+    ExcludePauses, // 一時停止の時間を速度の計算から除くかの切り替え
+    Countdown, // 開始前のカウントダウンの秒数。選ぶたびに COUNTDOWN_CHOICES を順に切り替える
+    TimeLimit, // 時間制限のある練習の秒数。選ぶたびに TIME_LIMIT_CHOICES を順に切り替える
+    HideReading, // 上段のルビを表示しないかの切り替え
+    HideTyped, // 下段の入力テキストを表示しないかの切り替え
+    Memorize, // 行を表示しておく秒数。選ぶたびに MEMORIZE_CHOICES を順に切り替える
+    ErrorPolicy, // ミスをした際の扱い。選ぶたびに ERROR_POLICY_CHOICES を順に切り替える
+}
+
+/// 結果画面の操作
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum ResultAction {
+    Menu, // メインメニューに戻る
+    Retry, // 同じ問題をもう一度入力する
+    Next, // 問題の一覧で次の問題を始める
+    Analysis, // 行ごとの詳しい分析を表示する
+}
+
+/// クリックやタップで操作できる項目
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum HitTarget {
+    MainMenuItem(usize),
+    ProblemItem(usize), // `App::problem_list` の番号
+    SettingsItem(usize), // `App::settings_items` の番号
+    PauseItem(usize),
+    ResultAction(ResultAction),
+}
+
+/// 分析画面に一度に表示する行の数
+pub const ANALYSIS_VISIBLE_ROWS: usize = 10;
+
+/// 選択できるカウントダウンの秒数
+pub const COUNTDOWN_CHOICES: [u32; 3] = [3, 5, 0];
+
+/// 選択できる制限時間の秒数。0は時間制限なし（問題を最後まで入力する）
+pub const TIME_LIMIT_CHOICES: [u32; 4] = [0, 60, 120, 300];
+
+/// 選択できる、暗記モードで行を表示しておく秒数。0は暗記モードなし（常に表示する）
+pub const MEMORIZE_CHOICES: [u32; 4] = [0, 3, 5, 10];
+
+/// 選択できるミスの扱い
+pub const ERROR_POLICY_CHOICES: [ErrorPolicy; 6] = [
+    ErrorPolicy::Continue,
+    ErrorPolicy::Strict,
+    ErrorPolicy::SuddenDeath { max_misses: 1 },
+    ErrorPolicy::SuddenDeath { max_misses: 5 },
+    ErrorPolicy::Penalty { seconds: 1 },
+    ErrorPolicy::Penalty { seconds: 3 },
+];
+
+/// 問題選択画面で選べる、入力する行数。Noneはすべての行
+pub const LINE_COUNT_CHOICES: [Option<usize>; 5] = [None, Some(5), Some(10), Some(20), Some(50)];
+
+/// 問題選択画面で数字を入力している設定
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum SessionOptionField {
+    Seed,
+    StartLine,
+}
+
+/// 数字で入力する設定の上限の桁数
+const MAX_OPTION_DIGITS: u32 = 9;
+
+/// 時間制限のある練習で問題を繰り返す量の目安にする、1秒あたりの最大の入力文字数
+const MAX_CHARS_PER_SECOND: u32 = 15;
+
+/// 設定画面に表示する組み込みの項目の一覧。読み込んだキー配列ファイルはこの後に並ぶ
+pub const SETTINGS_ITEMS: [SettingsItem; 15] = [
+    SettingsItem::Font(FontChoice::YujiSyuku),
+    SettingsItem::Font(FontChoice::NotoSerifJP),
+    SettingsItem::Layout(LayoutPreset::Standard),
+    SettingsItem::Layout(LayoutPreset::Azik),
+    SettingsItem::Layout(LayoutPreset::Act),
+    SettingsItem::Layout(LayoutPreset::Kunrei),
+    SettingsItem::Layout(LayoutPreset::Hepburn),
+    SettingsItem::Layout(LayoutPreset::JisKana),
+    SettingsItem::ExcludePauses,
+    SettingsItem::Countdown,
+    SettingsItem::TimeLimit,
+    SettingsItem::HideReading,
+    SettingsItem::HideTyped,
+    SettingsItem::Memorize,
+    SettingsItem::ErrorPolicy,
+];
+
 /// ロードされたフォントデータを保持する構造体
 pub struct Fonts<'a> {
     pub yuji_syuku: FontRef<'a>,
@@ -59,10 +202,13 @@ pub struct Fonts<'a> {
 }
 
 #[cfg(target_arch = "wasm32")]
-const MENU_ITEM_COUNT: usize = 2; // Quitなし
+const MENU_ITEM_COUNT: usize = 5; // Quitなし
 
 #[cfg(not(target_arch = "wasm32"))]
-const MENU_ITEM_COUNT: usize = 3;
+const MENU_ITEM_COUNT: usize = 6;
+
+/// 一時停止画面の項目の数（Resume, Restart, Quit）
+const PAUSE_ITEM_COUNT: usize = 3;
 
 /// アプリケーションで発生するイベントを定義するenum
 pub enum AppEvent {
@@ -75,6 +221,9 @@ pub enum AppEvent {
     Enter,
     Escape,
     CycleTuiMode,
+    FocusLost, // ウィンドウやブラウザのタブからフォーカスが外れた
+    Click { target: HitTarget }, // マウスのクリックやタッチのタップで項目を選んだ
+    Scroll { delta: i32 }, // マウスホイールの回転。正の値は下向き
     Quit,
 }
 
@@ -84,6 +233,7 @@ pub struct App<'a> {
     pub selected_main_menu_item: usize,
     pub selected_problem_item: usize,
     pub selected_settings_item: usize,
+    pub selected_pause_item: usize,
     pub problem_list: &'static [&'static str],
     pub typing_model: Option<TypingModel>,
     pub result_model: Option<ResultModel>,
@@ -94,11 +244,53 @@ pub struct App<'a> {
     // フォント管理用のフィールド
     pub fonts: Fonts<'a>,
     pub font_choice: FontChoice,
+    pub layout_choice: LayoutPreset,
+    pub custom_layouts: Vec<LayoutFile>, // 読み込んだキー配列ファイル
+    pub exclude_pauses: bool, // 速度の計算から、入力が途切れた時間を除く
+    pub countdown_seconds: u32, // タイピング開始前のカウントダウン。0ならすぐに入力を受け付ける
+    pub time_limit_seconds: u32, // 時間制限のある練習の制限時間。0なら問題を最後まで入力する
+    pub hide_reading: bool, // 上段のルビとローマ字のヒントを表示しない
+    pub hide_typed: bool, // 下段の入力テキストを表示しない
+    pub memorize_seconds: u32, // 暗記モードで、行を表示しておく秒数。0なら常に表示する
+    pub error_policy: ErrorPolicy, // ミスをした際の扱い
+    pub line_shown_at: Option<(i32, f64)>, // 入力中の行の番号と、その行を表示し始めた時刻
+    pub paused_at: Option<f64>, // セッションを一時停止した時刻。一時停止の間だけSome
+    pub session_options: SessionOptions, // 問題選択画面で指定する、入力する行と順序
+    pub editing_option: Option<SessionOptionField>, // 数字を入力中の設定
+    pub session_seed: Option<u64>, // 直前のセッションで行の並べ替えに使ったシード
+    pub review_queue: ReviewQueue, // 間違えた単語の復習の予定
+    pub review_problems: Option<Vec<String>>, // 復習のセッション中、各行の出題元の問題の名前
+    pub session_problem: String, // 入力中のセッションの問題の名前。復習の予定に記録する
+    pub session_problem_index: Option<usize>, // 入力中のセッションの問題の番号。問題の一覧から始めたセッションの間だけSome
+    pub analysis_scroll: usize, // 分析画面で先頭に表示している行
+    pub kana_stats: KanaStats, // かなごとの入力回数とミスの回数
+    pub history: Vec<HistoryEntry>, // 完了したセッションの記録。結果画面で同じ問題の最近の効率を示す
+    pub word_index: Option<WordIndex>, // すべての問題の単語の索引。最初に苦手なかなの練習を始める際に作る
+    pub endless: Option<EndlessStream>, // エンドレスモードの単語の供給元。エンドレスモードのセッションの間だけSome
+    pub karuta: Option<KarutaSession>, // かるたの練習の上の句とヒント。かるたの練習のセッションの間だけSome
+    pub spelling_preferences: SpellingPreferences, // セッションをまたいで保存する綴りの記録
+    pub custom_layout_choice: Option<usize>, // 選択中のキー配列ファイル。Someの間はlayout_choiceより優先する
     pub fps: f64, // FPSを保持するフィールドを追加
     #[cfg(target_arch = "wasm32")] // wasmでのみ利用
     pub should_reset_ime: bool, // IMEリセット要求フラグ
+    #[cfg(not(feature = "uefi"))] // UEFIではファイルの保存に対応しない
+    pub pending_export: Option<String>, // バックエンドによる保存を待っている結果のHTML
 }
 
+
+/// 選択肢の一覧で、`current` の次の値を返す（最後の次は最初に戻る）
+fn next_choice<T: Copy + PartialEq>(choices: &[T], current: T) -> T {
+    let index = choices.iter().position(|&choice| choice == current).unwrap_or(0);
+    choices[(index + 1) % choices.len()]
+}
+
+/// 綴りの記録を保存する際のキー
+const SPELLING_PREFERENCES_KEY: &str = "spelling_preferences";
+
+/// 結果をHTMLとして書き出す際のファイル名
+#[cfg(not(feature = "uefi"))]
+const RESULT_EXPORT_FILE_NAME: &str = "result.html";
+
 impl<'a> App<'a> {
     /// Appの新しいインスタンスを生成する
     pub fn new(fonts: Fonts<'a>) -> Self {
@@ -109,6 +301,7 @@ impl<'a> App<'a> {
             selected_main_menu_item: 0,
             selected_problem_item: 0,
             selected_settings_item: 0,
+            selected_pause_item: 0,
             problem_list: PROBLEM_FILES_NAMES,
             typing_model: None,
             result_model: None,
@@ -118,9 +311,42 @@ impl<'a> App<'a> {
             should_quit: false,
             fonts,
             font_choice: FontChoice::YujiSyuku, // デフォルトフォント
+            layout_choice: LayoutPreset::Standard, // デフォルトは一般的なローマ字入力
+            #[cfg(not(any(feature = "uefi", target_arch = "wasm32")))]
+            custom_layouts: load_layout_files(),
+            #[cfg(any(feature = "uefi", target_arch = "wasm32"))]
+            custom_layouts: Vec::new(),
+            custom_layout_choice: None,
+            exclude_pauses: false,
+            countdown_seconds: COUNTDOWN_CHOICES[0],
+            time_limit_seconds: TIME_LIMIT_CHOICES[0],
+            hide_reading: false,
+            hide_typed: false,
+            memorize_seconds: MEMORIZE_CHOICES[0],
+            error_policy: ErrorPolicy::default(),
+            line_shown_at: None,
+            paused_at: None,
+            session_options: SessionOptions::default(),
+            editing_option: None,
+            session_seed: None,
+            review_queue: storage::load(review::REVIEW_KEY).map(|text| ReviewQueue::from_text(&text)).unwrap_or_default(),
+            review_problems: None,
+            session_problem: String::new(),
+            session_problem_index: None,
+            analysis_scroll: 0,
+            kana_stats: storage::load(drill::KANA_STATS_KEY).map(|text| KanaStats::from_text(&text)).unwrap_or_default(),
+            history: storage::load(history::HISTORY_KEY).map(|text| history::parse_history(&text)).unwrap_or_default(),
+            word_index: None,
+            endless: None,
+            karuta: None,
+            spelling_preferences: storage::load(SPELLING_PREFERENCES_KEY)
+                .map(|text| SpellingPreferences::from_text(&text))
+                .unwrap_or_default(),
             fps: 0.0, // FPSを初期化
             #[cfg(target_arch = "wasm32")]
             should_reset_ime: false, // 初期値はfalse
+            #[cfg(not(feature = "uefi"))]
+            pending_export: None,
         }
     }
 
@@ -132,12 +358,158 @@ impl<'a> App<'a> {
         }
     }
 
+    /// セッションで更新された綴りの記録を取り込み、保存する
+    fn store_spelling_preferences(&mut self, preferences: &SpellingPreferences) {
+        self.spelling_preferences = preferences.clone();
+        storage::save(SPELLING_PREFERENCES_KEY, &self.spelling_preferences.to_text());
+    }
+
+    /// 設定に従って、表示に使う集計を求める
+    pub fn metrics_for(&self, model: &TypingModel) -> TypingMetrics {
+        let metrics = typing::calculate_total_metrics(model);
+        if self.exclude_pauses {
+            metrics.excluding_pauses()
+        } else {
+            metrics
+        }
+    }
+
+    /// 設定画面に表示する項目の一覧
+    pub fn settings_items(&self) -> Vec<SettingsItem> {
+        SETTINGS_ITEMS.iter().copied().chain((0..self.custom_layouts.len()).map(SettingsItem::CustomLayout)).collect()
+    }
+
+    /// 暗記モードで、入力中の行を伏せる時間になったか
+    pub fn is_line_hidden(&self, model: &TypingModel, now: f64) -> bool {
+        self.memorize_seconds > 0
+            && self
+                .line_shown_at
+                .is_some_and(|(line, shown_at)| line == model.status.line && now - shown_at >= self.memorize_seconds as f64 * 1000.0)
+    }
+
+    /// 選択中のキー配列の元になっている組み込みの配列
+    fn current_layout_base(&self) -> Option<LayoutPreset> {
+        match self.custom_layout_choice {
+            Some(index) => self.custom_layouts[index].base,
+            None => Some(self.layout_choice),
+        }
+    }
+
+    /// 選択中のキー配列を作る
+    fn current_layout(&self) -> Layout {
+        match self.custom_layout_choice {
+            Some(index) => self.custom_layouts[index].layout.clone(),
+            None => Layout::from_preset(self.layout_choice),
+        }
+    }
+
+    /// バックエンドがキー入力をJISかな配列のかなに変換すべきかどうか
+    ///
+    /// 日本語の問題を入力している間だけ変換し、メニュー操作や英字モードでは英数字のまま扱う。
+    pub fn uses_jis_kana_keys(&self) -> bool {
+        self.current_layout_base() == Some(LayoutPreset::JisKana)
+            && self.state == AppState::Typing
+            && self.typing_model.as_ref().is_some_and(|model| model.mode != InputMode::Latin)
+    }
+
     /// 新しいタイピングセッションを開始する
     fn start_typing_session(&mut self, problem_index: usize) {
         // 選択されたインデックスに基づいて問題文を読み込む
-        let problem_text = get_problem_content(problem_index);
-        let content = parser::parse_problem(problem_text);
+        let mut content = load_problem(problem_index);
+        if content.lines.is_empty() {
+            self.status_text = "This problem has no lines that can be typed. Lines with kanji but no ruby are skipped.".to_string();
+            return;
+        }
+        let seed = self.session_options.seed.unwrap_or_else(random::seed_from_time);
+        self.session_seed = self.session_options.shuffle.then_some(seed);
+        content = typing::apply_session_options(&content, &self.session_options, seed);
+        let mut karuta = None;
+        if self.session_options.karuta {
+            let Some((karuta_content, session)) = karuta::karuta_content(&content) else {
+                self.status_text = "This problem has no lines split into two halves with `|`.".to_string();
+                return;
+            };
+            content = karuta_content;
+            karuta = Some(session);
+        }
+        // 時間制限のある練習では、時間内に入力しきれない量まで問題の行を繰り返す
+        let time_limit = (self.time_limit_seconds > 0).then_some(TimeLimit {
+            duration: self.time_limit_seconds as f64 * 1000.0,
+            lap_lines: content.lines.len(),
+        });
+        if time_limit.is_some() {
+            content = typing::repeat_content(&content, (self.time_limit_seconds * MAX_CHARS_PER_SECOND) as usize);
+        }
+        self.review_problems = None;
+        self.session_problem = self.problem_list.get(problem_index).copied().unwrap_or_default().to_string();
+        self.session_problem_index = Some(problem_index);
+        self.begin_session(content, time_limit);
+        if karuta.is_some() {
+            self.karuta = karuta;
+            self.on_event(AppEvent::ChangeScene);
+        }
+    }
+
+    /// 苦手なかなを含む単語をすべての問題から集めて、練習のセッションを開始する
+    fn start_drill_session(&mut self) {
+        let problem_count = self.problem_list.len();
+        let index = self.word_index.get_or_insert_with(|| WordIndex::build((0..problem_count).map(load_problem)));
+        let Some(content) = drill::build_drill(index, &self.kana_stats, random::seed_from_time()) else {
+            self.status_text = "Not enough mistakes recorded for a drill yet.".to_string();
+            return;
+        };
+        self.review_problems = None;
+        self.session_problem = "Drill".to_string();
+        self.session_problem_index = None;
+        self.session_seed = None;
+        self.begin_session(content, None);
+    }
+
+    /// 復習の時刻を過ぎた単語で、復習のセッションを開始する
+    fn start_review_session(&mut self) {
+        let due = self.review_queue.due_items(crate::timestamp::now());
+        if due.is_empty() {
+            self.status_text = "No words are due for review.".to_string();
+            return;
+        }
+        let (content, problems) = review::review_content(&due);
+        self.review_problems = Some(problems);
+        self.session_problem = "Review".to_string();
+        self.session_problem_index = None;
+        self.session_seed = None;
+        self.begin_session(content, None);
+    }
+
+    /// 単語の一覧からランダムに選んだ単語を、終わりなく入力するセッションを開始する
+    ///
+    /// 時間制限を設定している場合は、制限時間で終わる。そうでなければESCで終えて結果を表示する。
+    fn start_endless_session(&mut self) {
+        let mut stream = endless::default_stream(random::seed_from_time());
+        let Some(content) = stream.initial_content() else {
+            self.status_text = "The word list is empty.".to_string();
+            return;
+        };
+        let time_limit = (self.time_limit_seconds > 0).then_some(TimeLimit {
+            duration: self.time_limit_seconds as f64 * 1000.0,
+            lap_lines: content.lines.len(),
+        });
+        self.review_problems = None;
+        self.session_problem = "Endless".to_string();
+        self.session_problem_index = None;
+        self.session_seed = None;
+        self.begin_session(content, time_limit);
+        self.endless = Some(stream);
+        self.on_event(AppEvent::ChangeScene);
+    }
+
+    /// 問題を入力するセッションを始め、タイピング画面に移る
+    fn begin_session(&mut self, content: Content, time_limit: Option<TimeLimit>) {
+        self.endless = None;
+        self.karuta = None;
+        self.line_shown_at = None;
+        self.paused_at = None;
         let typing_correctness = typing::create_typing_correctness_model(&content);
+        let mode = typing::resolve_input_mode(&content);
 
         self.typing_model = Some(TypingModel {
             content,
@@ -151,11 +523,18 @@ impl<'a> App<'a> {
             },
             user_input: Vec::new(),
             typing_correctness,
-            layout: Default::default(),
+            layout: self.current_layout(),
             scroll: Scroll {
                 scroll: 0.0,
                 max: 0.0,
             },
+            mode,
+            spelling: SpellingStats::default(),
+            preferences: self.spelling_preferences.clone(),
+            // カウントダウンの間に問題文が画面の右からスライドインする
+            start_time: Some(crate::timestamp::now() + self.countdown_seconds as f64 * 1000.0),
+            time_limit,
+            error_policy: self.error_policy,
         });
         self.result_model = None;
         self.state = AppState::Typing;
@@ -167,6 +546,205 @@ impl<'a> App<'a> {
         }
     }
 
+    /// タイピング中のセッションを一時停止し、一時停止画面に移る
+    fn pause_session(&mut self, status: &str) {
+        if self.typing_model.is_none() {
+            return;
+        }
+        self.paused_at = Some(crate::timestamp::now());
+        self.selected_pause_item = 0;
+        self.state = AppState::Paused;
+        self.on_event(AppEvent::ChangeScene);
+        self.status_text = status.to_string();
+    }
+
+    /// 一時停止していた時間を経過時間から除いて、タイピングを再開する
+    fn resume_session(&mut self) {
+        if let Some(paused_at) = self.paused_at.take() {
+            let paused = crate::timestamp::now() - paused_at;
+            if let Some(model) = self.typing_model.as_mut() {
+                typing::exclude_paused_time(model, paused);
+            }
+            if let Some((_, shown_at)) = self.line_shown_at.as_mut() {
+                *shown_at += paused;
+            }
+        }
+        self.state = AppState::Typing;
+        self.on_event(AppEvent::ChangeScene);
+    }
+
+    /// 同じ問題でセッションを最初からやり直す
+    ///
+    /// 行の選択や並べ替え、時間制限による繰り返しを済ませた問題をそのまま使う。エンドレスモードでは新しい単語で始め直す。
+    fn restart_session(&mut self, model: TypingModel) {
+        self.store_spelling_preferences(&model.preferences);
+        if self.endless.is_some() {
+            self.start_endless_session();
+            return;
+        }
+        let karuta = self.karuta.take().map(|session| KarutaSession { hint_line: 0, revealed: 0, hinted: Vec::new(), ..session });
+        self.begin_session(model.content, model.time_limit);
+        if karuta.is_some() {
+            self.karuta = karuta;
+            self.on_event(AppEvent::ChangeScene);
+        }
+    }
+
+    /// セッションを中断してメインメニューに戻る
+    ///
+    /// エンドレスモードは終わりがないため、入力を始めていればそこまでの結果を表示する。
+    fn quit_session(&mut self) {
+        self.paused_at = None;
+        let Some(model) = self.typing_model.take() else { return };
+        if self.endless.is_some() && !model.user_input.is_empty() {
+            self.show_result(typing::finish_session(model));
+            return;
+        }
+        // 中断したセッションで入力した綴りも記録に残す
+        self.store_spelling_preferences(&model.preferences);
+        self.state = AppState::MainMenu;
+        self.result_model = None;
+        self.on_event(AppEvent::ChangeScene);
+    }
+
+    /// 結果画面で選べる操作の一覧。次の問題は、問題の一覧から始めたセッションの後だけ選べる
+    pub fn result_actions(&self) -> Vec<ResultAction> {
+        let mut actions = vec![ResultAction::Menu, ResultAction::Retry];
+        if self.session_problem_index.is_some() {
+            actions.push(ResultAction::Next);
+        }
+        actions.push(ResultAction::Analysis);
+        actions
+    }
+
+    /// 結果画面の操作を行う。問題選択画面で選んだ問題と設定はそのまま残す
+    fn apply_result_action(&mut self, action: ResultAction) {
+        match action {
+            ResultAction::Menu => {
+                self.state = AppState::MainMenu;
+                self.typing_model = None;
+                self.result_model = None;
+                self.on_event(AppEvent::ChangeScene);
+            }
+            ResultAction::Retry => {
+                if let Some(result) = self.result_model.take() {
+                    self.restart_session(result.typing_model);
+                }
+            }
+            ResultAction::Next => {
+                let Some(index) = self.session_problem_index else { return };
+                self.selected_problem_item = (index + 1) % self.problem_list.len();
+                self.start_typing_session(self.selected_problem_item);
+            }
+            ResultAction::Analysis => {
+                self.analysis_scroll = 0;
+                self.state = AppState::Analysis;
+                self.on_event(AppEvent::ChangeScene);
+            }
+        }
+    }
+
+    /// 問題選択画面で、入力する行と順序の設定を操作する
+    ///
+    /// `L` で行数、`R` で順序、`K` でかるたの練習を切り替え、`S` と `N` に続けて数字を打つとシードと開始行を指定できる。
+    fn edit_session_options(&mut self, c: char) {
+        match c.to_ascii_lowercase() {
+            'l' => {
+                self.finish_option_editing();
+                let index = LINE_COUNT_CHOICES.iter().position(|&count| count == self.session_options.line_count).unwrap_or(0);
+                self.session_options.line_count = LINE_COUNT_CHOICES[(index + 1) % LINE_COUNT_CHOICES.len()];
+            }
+            'r' => {
+                self.finish_option_editing();
+                self.session_options.shuffle = !self.session_options.shuffle;
+            }
+            'k' => {
+                self.finish_option_editing();
+                self.session_options.karuta = !self.session_options.karuta;
+            }
+            's' => {
+                self.finish_option_editing();
+                self.session_options.seed = None;
+                self.editing_option = Some(SessionOptionField::Seed);
+            }
+            'n' => {
+                self.finish_option_editing();
+                self.session_options.start_line = 0;
+                self.editing_option = Some(SessionOptionField::StartLine);
+            }
+            _ => {
+                let Some(digit) = c.to_digit(10) else { return };
+                let push_digit = |value: u64| if value < 10u64.pow(MAX_OPTION_DIGITS - 1) { value * 10 + digit as u64 } else { value };
+                let options = &mut self.session_options;
+                match self.editing_option {
+                    Some(SessionOptionField::Seed) => options.seed = Some(push_digit(options.seed.unwrap_or(0))),
+                    Some(SessionOptionField::StartLine) => options.start_line = push_digit(options.start_line as u64) as usize,
+                    None => {}
+                }
+            }
+        }
+    }
+
+    /// 数字を入力中の設定の最後の1桁を消す
+    fn erase_option_digit(&mut self) {
+        let options = &mut self.session_options;
+        match self.editing_option {
+            Some(SessionOptionField::Seed) => options.seed = options.seed.map(|seed| seed / 10).filter(|&seed| seed > 0),
+            Some(SessionOptionField::StartLine) => options.start_line /= 10,
+            None => {}
+        }
+    }
+
+    /// 数字の入力を終える。開始行が空のままなら先頭から始める
+    fn finish_option_editing(&mut self) {
+        self.editing_option = None;
+        if self.session_options.start_line == 0 {
+            self.session_options.start_line = 1;
+        }
+    }
+
+    /// 完了したセッションで間違えた単語を復習の予定に記録し、保存する
+    fn record_review(&mut self, model: &TypingModel) {
+        let review_problems = self.review_problems.as_ref();
+        let session_problem = &self.session_problem;
+        let problem_for_line = |line: usize| match review_problems {
+            Some(problems) => problems.get(line).cloned().unwrap_or_default(),
+            None => session_problem.clone(),
+        };
+        self.review_queue.record_result(model, problem_for_line, crate::timestamp::now());
+        storage::save(review::REVIEW_KEY, &self.review_queue.to_text());
+    }
+
+    /// 完了したセッションを履歴に追記して保存する
+    fn record_history(&mut self, result: &ResultModel) {
+        let entry = HistoryEntry::from_result(result);
+        let mut text = storage::load(history::HISTORY_KEY).unwrap_or_default();
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(&entry.to_line());
+        text.push('\n');
+        storage::save(history::HISTORY_KEY, &text);
+        self.history.push(entry);
+    }
+
+    /// 完了したセッションのかなごとの正誤を記録し、保存する
+    fn record_kana_stats(&mut self, model: &TypingModel) {
+        self.kana_stats.record_result(model);
+        storage::save(drill::KANA_STATS_KEY, &self.kana_stats.to_text());
+    }
+
+    /// セッションを終えて結果画面に移る
+    fn show_result(&mut self, result_model: ResultModel) {
+        self.store_spelling_preferences(&result_model.typing_model.preferences);
+        self.record_history(&result_model);
+        self.record_review(&result_model.typing_model);
+        self.record_kana_stats(&result_model.typing_model);
+        self.result_model = Some(result_model);
+        self.state = AppState::Result;
+        self.on_event(AppEvent::ChangeScene);
+    }
+
     /// 毎フレームの状態更新（スクロール計算など）
     pub fn update(&mut self, width: usize, height: usize, delta_time: f64) {
         // FPSを計算して保存
@@ -177,11 +755,29 @@ impl<'a> App<'a> {
         if self.state != AppState::Typing {
             return;
         }
+
+        // 時間制限のある練習は、制限時間が来た時点で終える
+        let timed_out = self
+            .typing_model
+            .as_ref()
+            .and_then(|model| typing::time_remaining(model, crate::timestamp::now()))
+            .is_some_and(|remaining| remaining <= 0.0);
+        if timed_out {
+            if let Some(model) = self.typing_model.take() {
+                self.show_result(typing::finish_session(model));
+            }
+            return;
+        }
         // delta_timeが極端に大きい場合（デバッガで停止した場合など）にスクロールが飛びすぎるのを防ぐ
         // 100ms (0.1秒) を上限とする
         let clamped_delta_time = delta_time.min(100.0);
 
         if let Some(model) = self.typing_model.as_mut() {
+            // 行が表示され始めた時刻を記録する。最初の行はカウントダウンの終わりから数える
+            if self.line_shown_at.is_none_or(|(line, _)| line != model.status.line) {
+                let now = crate::timestamp::now();
+                self.line_shown_at = Some((model.status.line, model.start_time.map_or(now, |start| start.max(now))));
+            }
 
             // ブロック内で不変参照を取得することで借用ルール違反を回避
             let font = match self.font_choice {
@@ -281,9 +877,15 @@ impl<'a> App<'a> {
         if let AppEvent::ChangeScene = event {
             match self.state {
                 AppState::MainMenu => self.instructions_text = "Up/Down: Navigate | Enter: Select".to_string(),
-                AppState::ProblemSelection => self.instructions_text = "Up/Down: Select | Enter: Start | ESC: Back".to_string(),
-                AppState::Typing => self.instructions_text = "ESC: Back to Menu | Tab: Cycle Mode".to_string(),
-                AppState::Result => self.instructions_text = "Enter/ESC: Back to Menu".to_string(),
+                AppState::ProblemSelection => self.instructions_text = "Up/Down: Select | Enter: Start | L/R/K/S/N: Options | ESC: Back".to_string(),
+                AppState::Typing if self.karuta.is_some() => self.instructions_text = "ESC: Pause | Enter: Hint | Tab: Cycle Mode".to_string(),
+                AppState::Typing => self.instructions_text = "ESC: Pause | Tab: Cycle Mode".to_string(),
+                AppState::Paused => self.instructions_text = "Up/Down: Select | Enter: Apply | ESC: Resume".to_string(),
+                #[cfg(not(feature = "uefi"))]
+                AppState::Result => self.instructions_text = "Enter/ESC: Menu | R: Retry | N: Next | A: Analysis | E: Export HTML".to_string(),
+                #[cfg(feature = "uefi")]
+                AppState::Result => self.instructions_text = "Enter/ESC: Menu | R: Retry | N: Next | A: Analysis".to_string(),
+                AppState::Analysis => self.instructions_text = "Up/Down: Scroll | Enter/ESC: Back to Result".to_string(),
                 AppState::Settings => self.instructions_text = "Up/Down: Select | Enter: Apply | ESC: Back".to_string(),
             }
         }
@@ -294,16 +896,23 @@ impl<'a> App<'a> {
                 match event {
                     AppEvent::Up => if self.selected_main_menu_item > 0 { self.selected_main_menu_item -= 1; },
                     AppEvent::Down => if self.selected_main_menu_item < MENU_ITEM_COUNT - 1 { self.selected_main_menu_item += 1; },
+                    AppEvent::Click { target: HitTarget::MainMenuItem(index) } => {
+                        self.selected_main_menu_item = index;
+                        self.on_event(AppEvent::Enter);
+                    }
                     AppEvent::Enter => match self.selected_main_menu_item {
                         0 => {
                             self.state = AppState::ProblemSelection;
                             self.on_event(AppEvent::ChangeScene);
                         }
-                        1 => {
+                        1 => self.start_review_session(),
+                        2 => self.start_drill_session(),
+                        3 => self.start_endless_session(),
+                        4 => {
                             self.state = AppState::Settings;
                             self.on_event(AppEvent::ChangeScene);
                         }
-                        2 => {
+                        5 => {
                             #[cfg(not(target_arch = "wasm32"))]
                             { self.should_quit = true; }
                         }
@@ -313,15 +922,30 @@ impl<'a> App<'a> {
                 }
             }
             AppState::Settings => {
-                self.status_text = "Select a font.".to_string();
+                let items = self.settings_items();
                 match event {
                     AppEvent::Up => if self.selected_settings_item > 0 { self.selected_settings_item -= 1; },
-                    AppEvent::Down => if self.selected_settings_item < 1 { self.selected_settings_item += 1; },
+                    AppEvent::Down => if self.selected_settings_item < items.len() - 1 { self.selected_settings_item += 1; },
+                    AppEvent::Click { target: HitTarget::SettingsItem(index) } => {
+                        self.selected_settings_item = index;
+                        self.on_event(AppEvent::Enter);
+                    }
                     AppEvent::Enter => {
-                        self.font_choice = match self.selected_settings_item {
-                            0 => FontChoice::YujiSyuku,
-                            _ => FontChoice::NotoSerifJP,
-                        };
+                        match items[self.selected_settings_item] {
+                            SettingsItem::Font(font_choice) => self.font_choice = font_choice,
+                            SettingsItem::Layout(layout_choice) => {
+                                self.layout_choice = layout_choice;
+                                self.custom_layout_choice = None;
+                            }
+                            SettingsItem::CustomLayout(index) => self.custom_layout_choice = Some(index),
+                            SettingsItem::ExcludePauses => self.exclude_pauses = !self.exclude_pauses,
+                            SettingsItem::Countdown => self.countdown_seconds = next_choice(&COUNTDOWN_CHOICES, self.countdown_seconds),
+                            SettingsItem::TimeLimit => self.time_limit_seconds = next_choice(&TIME_LIMIT_CHOICES, self.time_limit_seconds),
+                            SettingsItem::HideReading => self.hide_reading = !self.hide_reading,
+                            SettingsItem::HideTyped => self.hide_typed = !self.hide_typed,
+                            SettingsItem::Memorize => self.memorize_seconds = next_choice(&MEMORIZE_CHOICES, self.memorize_seconds),
+                            SettingsItem::ErrorPolicy => self.error_policy = next_choice(&ERROR_POLICY_CHOICES, self.error_policy),
+                        }
                         self.state = AppState::MainMenu;
                         self.on_event(AppEvent::ChangeScene);
                     }
@@ -331,13 +955,50 @@ impl<'a> App<'a> {
                     }
                     _ => {}
                 }
+                if self.state == AppState::Settings {
+                    // キー配列ファイルに問題がある場合は、選択中にその内容を表示する
+                    let issues = match items[self.selected_settings_item] {
+                        SettingsItem::CustomLayout(index) => self.custom_layouts[index].issues.as_slice(),
+                        _ => &[],
+                    };
+                    self.status_text = match issues.first() {
+                        Some(issue) => format!("{} issue(s) in this layout file. {}", issues.len(), issue),
+                        None => "Select a font, keyboard layout or option.".to_string(),
+                    };
+                }
             }
             AppState::ProblemSelection => {
-                self.status_text = "Select a problem to type.".to_string();
+                self.status_text = match self.session_seed {
+                    Some(seed) => format!("Select a problem to type. Last seed: {}", seed),
+                    None => "Select a problem to type.".to_string(),
+                };
                 match event {
-                    AppEvent::Up => if self.selected_problem_item > 0 { self.selected_problem_item -= 1; },
-                    AppEvent::Down => if self.selected_problem_item < self.problem_list.len() - 1 { self.selected_problem_item += 1; },
-                    AppEvent::Enter => self.start_typing_session(self.selected_problem_item),
+                    AppEvent::Char { c, .. } => self.edit_session_options(c),
+                    AppEvent::Backspace => self.erase_option_digit(),
+                    AppEvent::Escape if self.editing_option.is_some() => self.finish_option_editing(),
+                    AppEvent::Up => {
+                        self.finish_option_editing();
+                        if self.selected_problem_item > 0 { self.selected_problem_item -= 1; }
+                    }
+                    AppEvent::Down => {
+                        self.finish_option_editing();
+                        if self.selected_problem_item < self.problem_list.len() - 1 { self.selected_problem_item += 1; }
+                    }
+                    AppEvent::Enter => {
+                        self.finish_option_editing();
+                        self.start_typing_session(self.selected_problem_item);
+                    }
+                    // 選択中の問題をもう一度選ぶと始める。誤って触れただけで始まらないようにする
+                    AppEvent::Click { target: HitTarget::ProblemItem(index) } if index == self.selected_problem_item => self.on_event(AppEvent::Enter),
+                    AppEvent::Click { target: HitTarget::ProblemItem(index) } => {
+                        self.finish_option_editing();
+                        self.selected_problem_item = index;
+                    }
+                    AppEvent::Scroll { delta } => {
+                        self.finish_option_editing();
+                        let last = self.problem_list.len().saturating_sub(1) as i32;
+                        self.selected_problem_item = (self.selected_problem_item as i32 + delta).clamp(0, last) as usize;
+                    }
                     AppEvent::Escape => {
                         self.state = AppState::MainMenu;
                         self.on_event(AppEvent::ChangeScene);
@@ -348,6 +1009,12 @@ impl<'a> App<'a> {
             AppState::Typing => {
                 self.status_text = "Start typing!".to_string();
                 match event {
+                    // カウントダウン中の打鍵は記録しない
+                    AppEvent::Char { timestamp, .. }
+                        if self.typing_model.as_ref().is_some_and(|model| typing::countdown_remaining(model, timestamp).is_some()) =>
+                    {
+                        self.status_text = "Get ready...".to_string();
+                    }
                     AppEvent::Char { c, timestamp } => {
                         if let Some(model) = self.typing_model.take() {
                             // key_input呼び出し前の状態を保存
@@ -355,7 +1022,22 @@ impl<'a> App<'a> {
                             let old_line = model.status.line;
 
                             match typing::key_input(model, c, timestamp) {
-                                Model::Typing(new_model) => {
+                                Model::Typing(mut new_model) => {
+                                    // ミスの扱いに応じて、ミスの後にすべきことを知らせる
+                                    if new_model.status.last_wrong_keydown.is_some() {
+                                        match new_model.error_policy {
+                                            ErrorPolicy::Strict => self.status_text = "Miss! Retype the word from the start.".to_string(),
+                                            ErrorPolicy::SuddenDeath { max_misses } => {
+                                                let left = (max_misses as usize).saturating_sub(typing::miss_count(&new_model));
+                                                self.status_text = format!("Miss! {} more and the session ends.", left);
+                                            }
+                                            _ => {}
+                                        }
+                                    }
+                                    // エンドレスモードでは、入力中の行の先の行を補充する
+                                    if let Some(stream) = self.endless.as_mut() {
+                                        stream.extend(&mut new_model);
+                                    }
                                     #[cfg(target_arch = "wasm32")]
                                     {
                                         // 単語または行が完了したかをチェック
@@ -365,37 +1047,89 @@ impl<'a> App<'a> {
                                     }
                                     self.typing_model = Some(new_model)
                                 },
-                                Model::Result(result_model) => {
-                                    self.result_model = Some(result_model);
-                                    self.state = AppState::Result;
-                                    self.on_event(AppEvent::ChangeScene);
-                                }
+                                Model::Result(result_model) => self.show_result(result_model),
                             }
                         }
                     }
-                    AppEvent::Escape => {
-                        self.state = AppState::MainMenu;
-                        self.typing_model = None;
-                        self.result_model = None;
-                        self.on_event(AppEvent::ChangeScene);
+                    // かるたの練習では、Enterで下の句を1セグメントずつ表示する
+                    AppEvent::Enter => {
+                        if let (Some(session), Some(model)) = (self.karuta.as_mut(), self.typing_model.as_ref()) {
+                            if !session.reveal_next(model) {
+                                self.status_text = "The whole line is already shown.".to_string();
+                            }
+                        }
                     }
+                    AppEvent::Escape => self.pause_session("Paused. The timer is stopped."),
+                    // 席を離れた間の時間を含めないよう、フォーカスが外れたら自動で一時停止する
+                    AppEvent::FocusLost => self.pause_session("Paused because the window lost focus."),
                     _ => {}
                 }
             }
+            AppState::Paused => match event {
+                AppEvent::Up => self.selected_pause_item = self.selected_pause_item.saturating_sub(1),
+                AppEvent::Down => self.selected_pause_item = (self.selected_pause_item + 1).min(PAUSE_ITEM_COUNT - 1),
+                AppEvent::Enter => match self.selected_pause_item {
+                    0 => self.resume_session(),
+                    1 => {
+                        if let Some(model) = self.typing_model.take() {
+                            self.restart_session(model);
+                        }
+                    }
+                    2 => self.quit_session(),
+                    _ => {}
+                },
+                AppEvent::Escape => self.resume_session(),
+                AppEvent::Click { target: HitTarget::PauseItem(index) } => {
+                    self.selected_pause_item = index;
+                    self.on_event(AppEvent::Enter);
+                }
+                _ => {}
+            },
             AppState::Result => {
                 if let Some(result) = &self.result_model {
-                    let metrics = typing::calculate_total_metrics(&result.typing_model);
-                    self.status_text = format!(
-                        "Complete! Speed: {:.2} kpm, Accuracy: {:.2}%",
-                        metrics.speed * 60.0,
-                        metrics.accuracy * 100.0
-                    );
+                    let metrics = self.metrics_for(&result.typing_model);
+                    self.status_text = if result.typing_model.mode == InputMode::Latin {
+                        format!(
+                            "Complete! Speed: {:.1} WPM (Net {:.1}), Accuracy: {:.2}%",
+                            metrics.wpm,
+                            metrics.net_wpm,
+                            metrics.accuracy * 100.0
+                        )
+                    } else {
+                        format!(
+                            "Complete! Speed: {:.2} kpm, Accuracy: {:.2}%",
+                            metrics.speed * 60.0,
+                            metrics.accuracy * 100.0
+                        )
+                    };
+                }
+                match event {
+                    AppEvent::Enter | AppEvent::Escape => self.apply_result_action(ResultAction::Menu),
+                    AppEvent::Char { c: 'r' | 'R', .. } => self.apply_result_action(ResultAction::Retry),
+                    AppEvent::Char { c: 'n' | 'N', .. } if self.session_problem_index.is_none() => {
+                        self.status_text = "Next is only available for problems from the list.".to_string();
+                    }
+                    AppEvent::Char { c: 'n' | 'N', .. } => self.apply_result_action(ResultAction::Next),
+                    AppEvent::Char { c: 'a' | 'A', .. } => self.apply_result_action(ResultAction::Analysis),
+                    AppEvent::Click { target: HitTarget::ResultAction(action) } => self.apply_result_action(action),
+                    #[cfg(not(feature = "uefi"))]
+                    AppEvent::Char { c: 'e' | 'E', .. } => {
+                        // 保存自体はファイルシステムを扱えるバックエンドが行う
+                        self.pending_export = self.export_result_html();
+                    }
+                    _ => {}
                 }
+            }
+            AppState::Analysis => {
+                self.status_text = "Speed, accuracy and misses for each line. The slowest line is highlighted.".to_string();
+                let line_count = self.result_model.as_ref().map_or(0, |result| typing::calculate_line_metrics(&result.typing_model).len());
+                let max_scroll = line_count.saturating_sub(ANALYSIS_VISIBLE_ROWS);
                 match event {
+                    AppEvent::Up => self.analysis_scroll = self.analysis_scroll.saturating_sub(1),
+                    AppEvent::Down => self.analysis_scroll = (self.analysis_scroll + 1).min(max_scroll),
+                    AppEvent::Scroll { delta } => self.analysis_scroll = (self.analysis_scroll as i32 + delta).clamp(0, max_scroll as i32) as usize,
                     AppEvent::Enter | AppEvent::Escape => {
-                        self.state = AppState::MainMenu;
-                        self.typing_model = None;
-                        self.result_model = None;
+                        self.state = AppState::Result;
                         self.on_event(AppEvent::ChangeScene);
                     }
                     _ => {}
@@ -403,4 +1137,24 @@ impl<'a> App<'a> {
             }
         }
     }
+
+    /// 直前のセッションの結果を、入力の正誤で色分けしたHTMLとして書き出す
+    pub fn export_result_html(&self) -> Option<String> {
+        let result = self.result_model.as_ref()?;
+        Some(html::export_html(&result.typing_model.content, Some(&result.typing_model.typing_correctness)))
+    }
+
+    /// 書き出しが要求されていれば、結果のHTMLを `write` で保存し、その成否をステータスに表示する
+    ///
+    /// `write` はファイル名とHTMLを受け取る。ファイルへの書き込みかダウンロードかは、バックエンドが決める。
+    #[cfg(not(feature = "uefi"))]
+    pub fn flush_pending_export(&mut self, write: impl FnOnce(&str, &str) -> Result<(), String>) {
+        let Some(html) = self.pending_export.take() else {
+            return;
+        };
+        self.status_text = match write(RESULT_EXPORT_FILE_NAME, &html) {
+            Ok(()) => format!("Exported to {}", RESULT_EXPORT_FILE_NAME),
+            Err(e) => format!("Export failed: {}", e),
+        };
+    }
 }
\ No newline at end of file
@@ -0,0 +1,102 @@
+// src/cli.rs
+
+use crate::aozora;
+use crate::html;
+use crate::layout_file;
+use crate::model::Content;
+use crate::parser;
+use crate::serializer;
+use std::fs;
+
+/// コマンドライン引数で指定されたサブコマンドを実行する
+///
+/// サブコマンドが指定されていない場合は `None` を返し、呼び出し側で通常どおりバックエンドを起動する。
+pub fn run(args: &[String]) -> Option<Result<(), Box<dyn std::error::Error>>> {
+    let command = args.first()?;
+    let result = match command.as_str() {
+        "import-aozora" => import_aozora(&args[1..]),
+        "import-html" => import_html(&args[1..]),
+        "export-html" => export_html(&args[1..]),
+        "check-layout" => check_layout(&args[1..]),
+        "help" | "--help" | "-h" => {
+            print_usage();
+            Ok(())
+        }
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn print_usage() {
+    println!("Usage:");
+    println!("  rust_multibackend_app                                  Start the application");
+    println!("  rust_multibackend_app import-aozora <input> [output]   Convert an Aozora Bunko text (UTF-8) to .ntq");
+    println!("  rust_multibackend_app import-html <input> [output]     Convert an HTML file with <ruby> markup to .ntq");
+    println!("  rust_multibackend_app export-html <input> [output]     Convert a .ntq or Aozora .txt file to HTML with <ruby> markup");
+    println!("  rust_multibackend_app check-layout <input>             Validate a .ntl layout file and report conflicts");
+}
+
+/// 変換結果を出力先ファイル、または標準出力に書き出す
+fn write_output(output: Option<&String>, text: &str) -> Result<(), Box<dyn std::error::Error>> {
+    match output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// 読みの付いていない漢字を含み、そのままでは入力できない本文の行を標準エラー出力に示す
+fn warn_lines_without_reading(command: &str, content: &Content) {
+    let lines = aozora::lines_without_reading(content);
+    for &index in &lines {
+        eprintln!("{}: body line {} has kanji without a reading: {}", command, index + 1, content.lines[index]);
+    }
+    if !lines.is_empty() {
+        eprintln!("{}: {} of {} lines cannot be typed until readings are added", command, lines.len(), content.lines.len());
+    }
+}
+
+/// 青空文庫形式のテキストを .ntq 形式に変換する
+fn import_aozora(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let input_path = args.first().ok_or("import-aozora: missing <input> file")?;
+    let input = fs::read_to_string(input_path)?;
+    let content = aozora::parse_aozora(&input);
+    warn_lines_without_reading("import-aozora", &content);
+    write_output(args.get(1), &serializer::serialize_problem(&content))
+}
+
+/// ルビ付きのHTMLを .ntq 形式に変換する
+fn import_html(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let input_path = args.first().ok_or("import-html: missing <input> file")?;
+    let input = fs::read_to_string(input_path)?;
+    let content = html::parse_ruby_html(&input);
+    warn_lines_without_reading("import-html", &content);
+    write_output(args.get(1), &serializer::serialize_problem(&content))
+}
+
+/// 問題ファイルをルビ付きのHTMLに変換する。拡張子が .txt の場合は青空文庫形式として読み込む
+fn export_html(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let input_path = args.first().ok_or("export-html: missing <input> file")?;
+    let input = fs::read_to_string(input_path)?;
+    let content: Content = if input_path.ends_with(".txt") {
+        aozora::parse_aozora(&input)
+    } else {
+        parser::parse_problem(&input)
+    };
+    write_output(args.get(1), &html::export_html(&content, None))
+}
+
+/// キー配列ファイルを読み込み、見つかった問題を表示する。問題があればエラーとして終了する
+fn check_layout(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let input_path = args.first().ok_or("check-layout: missing <input> file")?;
+    let input = fs::read_to_string(input_path)?;
+    let file = layout_file::parse_layout_file(&input, input_path);
+    for issue in &file.issues {
+        println!("{}: {}", input_path, issue);
+    }
+    if !file.issues.is_empty() {
+        return Err(format!("check-layout: {} issue(s) found in '{}'", file.issues.len(), file.name).into());
+    }
+    println!("{}: OK ({} kana entries)", file.name, file.layout.mapping.len());
+    Ok(())
+}
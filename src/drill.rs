@@ -0,0 +1,222 @@
+// src/drill.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+#[cfg(not(feature = "uefi"))]
+use std::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::model::{Content, InputMode, Line, ProblemOptions, Segment, TypingCorrectnessChar, TypingModel, Word};
+use crate::random::Random;
+use crate::storage::sanitize_field;
+use crate::typing;
+
+/// 保存する際のキー
+pub const KANA_STATS_KEY: &str = "kana_stats";
+
+/// 練習の対象にする苦手なかなの数
+pub const WEAK_KANA_COUNT: usize = 5;
+
+/// 1回の練習で出題する単語の数
+pub const DRILL_WORD_COUNT: usize = 20;
+
+/// 練習の1行に並べる単語の数
+const WORDS_PER_LINE: usize = 4;
+
+/// 苦手さを求める際に、入力回数に加える値。入力回数の少ないかなが、たまたまのミスで苦手と判定されるのを防ぐ
+const WEAKNESS_SMOOTHING: f64 = 5.0;
+
+/// 統計の対象にするかな（平仮名に直した後の文字）か
+fn is_kana(c: char) -> bool {
+    ('ぁ'..='ゖ').contains(&c) || c == 'ー'
+}
+
+/// かなごとの入力回数とミスの回数
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct KanaStats {
+    pub counts: Vec<(char, u32, u32)>, // (かな, 入力回数, ミスの回数)
+}
+
+impl KanaStats {
+    /// `to_text` で保存したテキストから読み込む。読めない行は無視する
+    pub fn from_text(text: &str) -> Self {
+        let counts = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let mut kana = fields.next()?.chars();
+                let c = kana.next().filter(|_| kana.next().is_none())?;
+                Some((c, fields.next()?.parse().ok()?, fields.next()?.parse().ok()?))
+            })
+            .collect();
+        KanaStats { counts }
+    }
+
+    /// 保存用のテキストに変換する。1行に1つのかなを、タブ区切りで書く
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for (kana, attempts, misses) in &self.counts {
+            text.push_str(&format!("{}\t{}\t{}\n", sanitize_field(&kana.to_string()), attempts, misses));
+        }
+        text
+    }
+
+    fn record(&mut self, kana: char, missed: bool) {
+        let index = match self.counts.iter().position(|(c, ..)| *c == kana) {
+            Some(index) => index,
+            None => {
+                self.counts.push((kana, 0, 0));
+                self.counts.len() - 1
+            }
+        };
+        self.counts[index].1 += 1;
+        if missed {
+            self.counts[index].2 += 1;
+        }
+    }
+
+    /// 完了したセッションで入力を終えた単語の、かなごとの正誤を記録する。英字モードのセッションは対象にしない
+    pub fn record_result(&mut self, model: &TypingModel) {
+        if model.mode == InputMode::Latin {
+            return;
+        }
+        for (line_idx, word_idx) in typing::completed_word_positions(model) {
+            let reading = typing::word_reading(&model.content.lines[line_idx].words[word_idx]);
+            let Some(correctness) = model.typing_correctness.lines.get(line_idx).and_then(|line| line.words.get(word_idx)) else {
+                continue;
+            };
+            let chars = correctness.segments.iter().flat_map(|segment| &segment.chars);
+            for (c, state) in reading.chars().map(typing::to_hiragana).zip(chars) {
+                if is_kana(c) {
+                    self.record(c, *state == TypingCorrectnessChar::Incorrect);
+                }
+            }
+        }
+    }
+
+    /// かなの苦手さ。ミスの割合を、入力回数が少ないほど小さく見積もる
+    pub fn weakness(&self, kana: char) -> f64 {
+        self.counts
+            .iter()
+            .find(|(c, ..)| *c == kana)
+            .map_or(0.0, |(_, attempts, misses)| *misses as f64 / (*attempts as f64 + WEAKNESS_SMOOTHING))
+    }
+
+    /// ミスをしたことのあるかなを、苦手な順に最大 `count` 個返す
+    pub fn weakest(&self, count: usize) -> Vec<char> {
+        let mut kana: Vec<char> = self.counts.iter().filter(|(.., misses)| *misses > 0).map(|(c, ..)| *c).collect();
+        kana.sort_by(|a, b| self.weakness(*b).total_cmp(&self.weakness(*a)));
+        kana.truncate(count);
+        kana
+    }
+}
+
+/// 単語の索引の1項目
+#[derive(Debug, Clone)]
+pub struct IndexedWord {
+    pub word: Word,
+    pub reading: String, // 平仮名に直した読み
+}
+
+/// 読み込んだすべての問題に含まれる日本語の単語の索引
+#[derive(Debug, Clone, Default)]
+pub struct WordIndex {
+    pub words: Vec<IndexedWord>,
+}
+
+impl WordIndex {
+    /// 問題から索引を作る。英字モードの問題と、かなを含まない単語は除き、同じ読みの単語は1つにまとめる
+    pub fn build(contents: impl IntoIterator<Item = Content>) -> Self {
+        let mut words: Vec<IndexedWord> = Vec::new();
+        for content in contents {
+            if typing::resolve_input_mode(&content) == InputMode::Latin {
+                continue;
+            }
+            for word in content.lines.into_iter().flat_map(|line| line.words) {
+                let reading: String = typing::word_reading(&word).chars().map(typing::to_hiragana).collect();
+                if !reading.chars().any(is_kana) || words.iter().any(|indexed| indexed.reading == reading) {
+                    continue;
+                }
+                words.push(IndexedWord { word, reading });
+            }
+        }
+        WordIndex { words }
+    }
+
+    /// 指定したかなを含む単語
+    pub fn containing(&self, kana: char) -> impl Iterator<Item = &IndexedWord> {
+        self.words.iter().filter(move |indexed| indexed.reading.contains(kana))
+    }
+}
+
+/// 苦手なかなを多く含む単語を集めた練習用の問題を作る
+///
+/// 苦手さの合計が大きい単語を候補とし、候補の中から `seed` で選んで並べる。
+/// ミスの記録がない場合や、苦手なかなを含む単語がない場合は `None` を返す。
+pub fn build_drill(index: &WordIndex, stats: &KanaStats, seed: u64) -> Option<Content> {
+    let weak = stats.weakest(WEAK_KANA_COUNT);
+    if weak.is_empty() {
+        return None;
+    }
+    let mut candidates: Vec<(f64, &IndexedWord)> = index
+        .words
+        .iter()
+        .map(|indexed| {
+            let score: f64 = weak.iter().filter(|kana| indexed.reading.contains(**kana)).map(|kana| stats.weakness(*kana)).sum();
+            (score, indexed)
+        })
+        .filter(|(score, _)| *score > 0.0)
+        .collect();
+    if candidates.is_empty() {
+        return None;
+    }
+    // 毎回同じ単語にならないよう、上位の候補から選ぶ
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+    candidates.truncate(DRILL_WORD_COUNT * 3);
+    Random::new(seed).shuffle(&mut candidates);
+    candidates.truncate(DRILL_WORD_COUNT);
+
+    let lines = candidates.chunks(WORDS_PER_LINE).map(|chunk| Line { words: chunk.iter().map(|(_, indexed)| indexed.word.clone()).collect(), split: None }).collect();
+    let weak_text: Vec<String> = weak.iter().map(|kana| kana.to_string()).collect();
+    let title = Line { words: vec![Word { segments: vec![Segment::Plain { text: format!("Drill: {}", weak_text.join(" ")) }] }], split: None };
+    Some(Content { title, lines, options: ProblemOptions { mode: InputMode::Japanese, ..ProblemOptions::default() } })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_problem;
+
+    #[test]
+    fn test_weak_kana_drill() {
+        // 苦手なかなを含む単語だけが、索引から選ばれることを確認
+        let mut stats = KanaStats::from_text("し\t20\t6\nか\t50\t1\nあ\t3\t0\n");
+        assert_eq!(stats.weakest(5), vec!['し', 'か']);
+        stats.record('つ', true);
+        assert_eq!(KanaStats::from_text(&stats.to_text()), stats);
+
+        let index = WordIndex::build([
+            parse_problem("#title a\n(寿司/すし)/(海/うみ)/(机/つくえ)\n"),
+            parse_problem("#title b\n(試験/シケン)/(海/うみ)\n"),
+            parse_problem("#title c\nhello world\n"),
+        ]);
+        assert_eq!(index.words.len(), 4);
+        assert_eq!(index.containing('し').count(), 2);
+
+        let drill = build_drill(&index, &stats, 1).unwrap();
+        let mut readings: Vec<String> = drill.lines.iter().flat_map(|line| &line.words).map(typing::word_reading).collect();
+        readings.sort();
+        assert_eq!(readings, vec!["すし", "つくえ", "シケン"]);
+        assert!(build_drill(&index, &KanaStats::default(), 1).is_none());
+    }
+}
@@ -0,0 +1,150 @@
+// src/endless.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::{string::ToString, vec, vec::Vec};
+#[cfg(not(feature = "uefi"))]
+use std::{string::ToString, vec::Vec};
+
+use crate::model::{Content, InputMode, Line, ProblemOptions, Segment, TypingModel, Word};
+use crate::random::Random;
+use crate::typing;
+
+/// 組み込みの単語の一覧（`表記<TAB>読み` を1行に1語）
+pub const WORD_LIST: &str = include_str!("../wordlists/common.tsv");
+
+/// 入力中の行より後に、常に用意しておく行の数
+pub const ENDLESS_LOOKAHEAD: usize = 3;
+
+/// 1行に並べる単語の数
+const WORDS_PER_LINE: usize = 4;
+
+/// 単語の一覧を読み込む
+///
+/// 1行に1語を `表記<TAB>読み` の形で書き、`#` で始まる行と空行は無視する。
+/// 読みを省略した場合や表記と同じ場合は、ルビのない単語として扱う。
+pub fn parse_word_list(text: &str) -> Vec<Word> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let mut fields = line.split('\t').map(str::trim);
+            let base = fields.next().unwrap_or_default().to_string();
+            let segment = match fields.next().filter(|reading| !reading.is_empty() && *reading != base) {
+                Some(reading) => Segment::Annotated { base, reading: reading.to_string() },
+                None => Segment::Plain { text: base },
+            };
+            Word { segments: vec![segment] }
+        })
+        .collect()
+}
+
+/// 単語の一覧から、ランダムな単語の行を必要な分だけ作り続ける
+#[derive(Debug, Clone)]
+pub struct EndlessStream {
+    words: Vec<Word>,
+    random: Random,
+    last: Option<usize>, // 直前に出題した単語。同じ単語が続かないようにする
+}
+
+impl EndlessStream {
+    pub fn new(words: Vec<Word>, seed: u64) -> Self {
+        Self { words, random: Random::new(seed), last: None }
+    }
+
+    /// 次の1行を作る
+    fn next_line(&mut self) -> Line {
+        let mut words = Vec::with_capacity(WORDS_PER_LINE);
+        for _ in 0..WORDS_PER_LINE {
+            let mut index = self.random.below(self.words.len());
+            if self.words.len() > 1 && Some(index) == self.last {
+                index = (index + 1 + self.random.below(self.words.len() - 1)) % self.words.len();
+            }
+            self.last = Some(index);
+            words.push(self.words[index].clone());
+        }
+        Line { words, split: None }
+    }
+
+    /// 最初に表示する問題を作る。単語の一覧が空の場合は `None` を返す
+    pub fn initial_content(&mut self) -> Option<Content> {
+        if self.words.is_empty() {
+            return None;
+        }
+        let lines = (0..=ENDLESS_LOOKAHEAD).map(|_| self.next_line()).collect();
+        let title = Line { words: vec![Word { segments: vec![Segment::Plain { text: "Endless".to_string() }] }], split: None };
+        Some(Content { title, lines, options: ProblemOptions { mode: InputMode::Japanese, ..ProblemOptions::default() } })
+    }
+
+    /// 入力中の行より後の行が `ENDLESS_LOOKAHEAD` 行になるまで、問題と正誤の記録に行を追加する
+    pub fn extend(&mut self, model: &mut TypingModel) {
+        while model.content.lines.len() < model.status.line as usize + 1 + ENDLESS_LOOKAHEAD {
+            let line = self.next_line();
+            typing::append_line(model, line);
+        }
+    }
+}
+
+/// 組み込みの単語の一覧で `EndlessStream` を作る
+pub fn default_stream(seed: u64) -> EndlessStream {
+    EndlessStream::new(parse_word_list(WORD_LIST), seed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Layout, Model};
+    use crate::typing::tests::{every_100ms, new_model_with_layout, type_keys};
+
+    #[test]
+    fn test_parse_word_list() {
+        // コメントと空行を無視し、読みがない単語や読みが表記と同じ単語はルビなしになることを確認
+        let words = parse_word_list("# comment\n朝\tあさ\nあ\n\n  # indented comment\nいぬ\tいぬ\n犬\t\n");
+        assert_eq!(words.len(), 4);
+        assert!(matches!(&words[0].segments[0], Segment::Annotated { base, reading } if base == "朝" && reading == "あさ"));
+        assert!(matches!(&words[1].segments[0], Segment::Plain { text } if text == "あ"));
+        assert!(matches!(&words[2].segments[0], Segment::Plain { text } if text == "いぬ"));
+        assert!(matches!(&words[3].segments[0], Segment::Plain { text } if text == "犬"));
+        assert!(parse_word_list("# only comments\n\n").is_empty());
+    }
+
+    #[test]
+    fn test_no_back_to_back_repeats() {
+        // 行の中でも行をまたいでも、同じ単語が続けて出題されないことを確認
+        let mut stream = EndlessStream::new(parse_word_list("あ\nい\n"), 3);
+        let words: Vec<Word> = (0..50).flat_map(|_| stream.next_line().words).collect();
+        assert!(words.windows(2).all(|pair| pair[0] != pair[1]));
+        // 単語が1つしかなければ、同じ単語を繰り返す
+        let mut stream = EndlessStream::new(parse_word_list("あ\n"), 3);
+        assert_eq!(stream.next_line().words.len(), WORDS_PER_LINE);
+    }
+
+    #[test]
+    fn test_initial_content() {
+        // 最初の問題に入力中の行と先読みの行が用意され、単語の一覧が空なら問題を作らないことを確認
+        let content = default_stream(1).initial_content().unwrap();
+        assert_eq!(content.lines.len(), 1 + ENDLESS_LOOKAHEAD);
+        assert_eq!(content.options.mode, InputMode::Japanese);
+        assert!(EndlessStream::new(Vec::new(), 0).initial_content().is_none());
+        assert!(EndlessStream::new(parse_word_list("# comment\n"), 0).initial_content().is_none());
+    }
+
+    #[test]
+    fn test_endless_stream() {
+        // 入力が進むたびに行が補充され、問題の終わりに達しないことを確認
+        let mut stream = EndlessStream::new(parse_word_list("あ\n"), 7);
+        let mut model = new_model_with_layout("#title test\nあ\n", Layout::default());
+        model.content = stream.initial_content().unwrap();
+        model.typing_correctness = typing::create_typing_correctness_model(&model.content);
+        for timestamp in every_100ms().take(20) {
+            let Model::Typing(next) = type_keys(model, "a", [timestamp]) else { panic!("an endless session must not finish") };
+            model = next;
+            stream.extend(&mut model);
+        }
+        assert_eq!(model.status.line, 5);
+        assert_eq!(model.content.lines.len(), 5 + 1 + ENDLESS_LOOKAHEAD);
+        assert_eq!(model.typing_correctness.lines.len(), model.content.lines.len());
+    }
+}
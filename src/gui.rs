@@ -2,6 +2,8 @@
 
 #[cfg(not(feature = "uefi"))] // Only compile if uefi feature is NOT enabled
 use crate::app::{App, AppEvent, Fonts}; // Fontsをインポート
+#[cfg(not(feature = "uefi"))]
+use crate::layout_data;
 #[cfg(not(feature = "uefi"))] // Only compile if uefi feature is NOT enabled
 use crate::renderer::{calculate_pixel_font_size, draw_linear_gradient, gui_renderer};
 #[cfg(not(feature = "uefi"))] // Only compile if uefi feature is NOT enabled
@@ -9,7 +11,7 @@ use crate::ui::{self, ActiveLowerElement, LowerTypingSegment, Renderable, UpperS
 #[cfg(not(feature = "uefi"))] // Only compile if uefi feature is NOT enabled
 use ab_glyph::FontRef;
 #[cfg(not(feature = "uefi"))] // Only compile if uefi feature is NOT enabled
-use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
 #[cfg(not(feature = "uefi"))]
 use std::time::Instant;
 
@@ -96,6 +98,8 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
     app.on_event(AppEvent::Start);
 
     let mut last_frame_time = Instant::now();
+    let mut was_active = true;
+    let mut was_mouse_down = false;
 
     while window.is_open() && !app.should_quit {
         let (new_width, new_height) = window.get_size();
@@ -109,6 +113,17 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
         last_frame_time = now_time;
 
         handle_input(&mut window, &mut app);
+        handle_mouse(&window, &mut app, width, height, &mut was_mouse_down);
+
+        // ウィンドウからフォーカスが外れたことをAppに知らせる
+        let is_active = window.is_active();
+        if was_active && !is_active {
+            app.on_event(AppEvent::FocusLost);
+        }
+        was_active = is_active;
+
+        // 結果のHTML書き出しが要求されていればファイルに保存する
+        app.flush_pending_export(|file_name, html| std::fs::write(file_name, html).map_err(|e| e.to_string()));
 
         app.update(width, height, delta_time);
 
@@ -148,17 +163,21 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                             UpperSegmentState::Active => ui::ACTIVE_COLOR,
                             UpperSegmentState::Pending => ui::PENDING_COLOR,
                         };
-                        gui_renderer::draw_text(&mut pixel_buffer, width, current_font, &seg.base_text, (pen_x as f32, y as f32), pixel_font_size, color);
-                        
-                        if let Some(ruby) = &seg.ruby_text {
-                            let (base_w, ..) = gui_renderer::measure_text(current_font, &seg.base_text, pixel_font_size);
-                            let (ruby_w, ..) = gui_renderer::measure_text(current_font, ruby, ruby_pixel_font_size);
-                            let ruby_x = pen_x as f32 + (base_w as f32 - ruby_w as f32) / 2.0;
-                            let ruby_y = y as f32 - ruby_pixel_font_size*0.5;
-                            gui_renderer::draw_text(&mut pixel_buffer, width, current_font, ruby, (ruby_x, ruby_y), ruby_pixel_font_size, color);
-                        }
-                        
                         let (seg_width, _, _) = gui_renderer::measure_text(current_font, &seg.base_text, pixel_font_size);
+                        if seg.hidden {
+                            let (mark_x, mark_y, mark_w, mark_h) = ui::hidden_mark_rect((pen_x, y), seg_width, pixel_font_size);
+                            gui_renderer::draw_rect(&mut pixel_buffer, width, mark_x, mark_y, mark_w, mark_h, ui::PENDING_COLOR);
+                        } else {
+                            gui_renderer::draw_text(&mut pixel_buffer, width, current_font, &seg.base_text, (pen_x as f32, y as f32), pixel_font_size, color);
+
+                            if let Some(ruby) = &seg.ruby_text {
+                                let (ruby_w, ..) = gui_renderer::measure_text(current_font, ruby, ruby_pixel_font_size);
+                                let ruby_x = pen_x as f32 + (seg_width as f32 - ruby_w as f32) / 2.0;
+                                let ruby_y = y as f32 - ruby_pixel_font_size*0.5;
+                                gui_renderer::draw_text(&mut pixel_buffer, width, current_font, ruby, (ruby_x, ruby_y), ruby_pixel_font_size, color);
+                            }
+                        }
+
                         pen_x += seg_width as i32;
                     }
                 }
@@ -180,6 +199,15 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                         gui_renderer::draw_rect(&mut pixel_buffer, width, start_x, start_y, fg_width, bar_height as usize, fg_color);
                     }
                 }
+                Renderable::LineChart { series, x_max, x_label, anchor, shift, align, width_ratio, height_ratio, font_size } => {
+                    let chart_width = (width as f32 * width_ratio) as u32;
+                    let chart_height = (height as f32 * height_ratio) as u32;
+                    let pixel_font_size = calculate_pixel_font_size(font_size, width, height);
+                    let anchor_pos = ui::calculate_anchor_position(anchor, shift, width, height);
+                    let (x, y) = ui::calculate_aligned_position(anchor_pos, chart_width, chart_height, align);
+                    let rect = (x as f32, y as f32, chart_width as f32, chart_height as f32);
+                    gui_renderer::draw_line_chart(&mut pixel_buffer, width, current_font, rect, &series, x_max, &x_label, pixel_font_size);
+                }
                 Renderable::TypingLower { segments, anchor, shift, align, font_size, target_line_total_width } => {
                     let pixel_font_size = calculate_pixel_font_size(font_size, width, height);
                     let ruby_pixel_font_size = pixel_font_size * 0.3;
@@ -236,7 +264,14 @@ fn handle_input(window: &mut Window, app: &mut App) {
             Key::Enter => app.on_event(AppEvent::Enter),
             Key::Escape => app.on_event(AppEvent::Escape),
             _ => {
-                if let Some(char_key) = key_to_char(key, window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift)) {
+                let is_shift = window.is_key_down(Key::LeftShift) || window.is_key_down(Key::RightShift);
+                // JISかな配列では、キーの位置に対応するかなを入力する（かなのないキーは英数字のまま）
+                let char_key = if app.uses_jis_kana_keys() {
+                    key_to_jis_key(key).and_then(|jis_key| layout_data::jis_kana_for_key(jis_key, is_shift))
+                } else {
+                    None
+                };
+                if let Some(char_key) = char_key.or_else(|| key_to_char(key, is_shift)) {
                     app.on_event(AppEvent::Char { c: char_key, timestamp: crate::timestamp::now() });
                 }
             }
@@ -244,6 +279,28 @@ fn handle_input(window: &mut Window, app: &mut App) {
     }
 }
 
+/// マウスの左ボタンを押した位置の項目と、ホイールの回転をAppに知らせる
+#[cfg(not(feature = "uefi"))]
+fn handle_mouse(window: &Window, app: &mut App, width: usize, height: usize, was_mouse_down: &mut bool) {
+    let is_mouse_down = window.get_mouse_down(MouseButton::Left);
+    if is_mouse_down && !*was_mouse_down {
+        if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+            let regions = ui::build_hit_regions(app, app.get_current_font(), width, height);
+            if let Some(target) = ui::hit_test(&regions, x as i32, y as i32) {
+                app.on_event(AppEvent::Click { target });
+            }
+        }
+    }
+    *was_mouse_down = is_mouse_down;
+
+    // minifbのホイールは上向きが正
+    if let Some((_, scroll_y)) = window.get_scroll_wheel() {
+        if scroll_y != 0.0 {
+            app.on_event(AppEvent::Scroll { delta: if scroll_y > 0.0 { -1 } else { 1 } });
+        }
+    }
+}
+
 #[cfg(not(feature = "uefi"))]
 fn key_to_char(key: Key, is_shift: bool) -> Option<char> {
     match (key, is_shift) {
@@ -294,6 +351,28 @@ fn key_to_char(key: Key, is_shift: bool) -> Option<char> {
     }
 }
 
+/// JISキーボードのキーを、英数モードでそのキーに刻印されている文字に変換する
+///
+/// 記号キーはWindowsの仮想キーコードに基づいて対応付ける（JIS配列では `[` の位置に `@` がある、など）。
+#[cfg(not(feature = "uefi"))]
+fn key_to_jis_key(key: Key) -> Option<char> {
+    match key {
+        Key::Minus => Some('-'),
+        Key::Apostrophe => Some('^'),
+        Key::Backslash => Some('¥'),
+        Key::Backquote => Some('@'),
+        Key::LeftBracket => Some('['),
+        Key::Equal => Some(';'),
+        Key::Semicolon => Some(':'),
+        Key::RightBracket => Some(']'),
+        Key::Comma => Some(','),
+        Key::Period => Some('.'),
+        Key::Slash => Some('/'),
+        // 英字と数字のキーは刻印どおり
+        _ => key_to_char(key, false).filter(|c| c.is_ascii_alphanumeric()),
+    }
+}
+
 #[cfg(feature = "uefi")]
 pub fn run() -> Result<(), Box<dyn core::error::Error>> {
     Err("GUI is not supported in UEFI environment.".into())
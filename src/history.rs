@@ -0,0 +1,123 @@
+// src/history.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(not(feature = "uefi"))]
+use std::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::model::ResultModel;
+use crate::storage::sanitize_field;
+use crate::typing;
+
+/// 保存する際のキー
+pub const HISTORY_KEY: &str = "history";
+
+/// 完了したセッション1回分の記録
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub timestamp: f64, // 最後に入力した時刻（ミリ秒）
+    pub title: String,
+    pub type_count: i32,
+    pub miss_count: i32,
+    pub total_time: f64, // ミリ秒
+    pub efficiency: f64, // 最少の打鍵数 / 実際の打鍵数
+}
+
+impl HistoryEntry {
+    /// 結果から記録を作る
+    pub fn from_result(result: &ResultModel) -> Self {
+        let model = &result.typing_model;
+        let metrics = typing::calculate_total_metrics(model);
+        let timestamp = model.user_input.iter().filter_map(|s| s.inputs.last()).map(|i| i.timestamp).fold(0.0, f64::max);
+        HistoryEntry {
+            timestamp,
+            title: model.content.title.to_string(),
+            type_count: metrics.type_count,
+            miss_count: metrics.miss_count,
+            total_time: metrics.total_time,
+            efficiency: result.efficiency.ratio,
+        }
+    }
+
+    /// 保存用の1行に変換する。項目はタブで区切り、タイトル中のタブと改行は空白に置き換える
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{:.4}",
+            self.timestamp, sanitize_field(&self.title), self.type_count, self.miss_count, self.total_time, self.efficiency
+        )
+    }
+
+    /// `to_line` で保存した1行から読み込む
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        Some(HistoryEntry {
+            timestamp: fields.next()?.parse().ok()?,
+            title: fields.next()?.to_string(),
+            type_count: fields.next()?.parse().ok()?,
+            miss_count: fields.next()?.parse().ok()?,
+            total_time: fields.next()?.parse().ok()?,
+            efficiency: fields.next()?.parse().ok()?,
+        })
+    }
+}
+
+/// 保存した記録をすべて読み込む。読めない行は無視する
+pub fn parse_history(text: &str) -> Vec<HistoryEntry> {
+    text.lines().filter_map(HistoryEntry::from_line).collect()
+}
+
+/// `title` の問題の記録のうち新しいものを最大 `count` 件、古い順に返す
+pub fn recent_entries<'a>(entries: &'a [HistoryEntry], title: &str, count: usize) -> Vec<&'a HistoryEntry> {
+    let mut recent: Vec<&HistoryEntry> = entries.iter().rev().filter(|entry| entry.title == title).take(count).collect();
+    recent.reverse();
+    recent
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_history_line_round_trip() {
+        // 保存した行から同じ記録が読み込めること、壊れた行が無視されることを確認
+        let entry = HistoryEntry {
+            timestamp: 1700000000000.0,
+            title: "百人\t一首".to_string(),
+            type_count: 120,
+            miss_count: 3,
+            total_time: 45678.0,
+            efficiency: 0.9231,
+        };
+        let text = format!("{}\nbroken line\n", entry.to_line());
+        let entries = parse_history(&text);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0], HistoryEntry { title: "百人 一首".to_string(), ..entry });
+    }
+
+    #[test]
+    fn test_recent_entries() {
+        // 同じ問題の新しい記録だけが、古い順に選ばれることを確認
+        let entry = |title: &str, efficiency: f64| HistoryEntry {
+            timestamp: 0.0,
+            title: title.to_string(),
+            type_count: 10,
+            miss_count: 0,
+            total_time: 1000.0,
+            efficiency,
+        };
+        let entries = [entry("a", 0.5), entry("b", 0.6), entry("a", 0.7), entry("a", 0.8), entry("a", 0.9)];
+        let recent: Vec<f64> = recent_entries(&entries, "a", 3).iter().map(|entry| entry.efficiency).collect();
+        assert_eq!(recent, vec![0.7, 0.8, 0.9]);
+        assert!(recent_entries(&entries, "c", 3).is_empty());
+    }
+}
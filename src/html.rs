@@ -0,0 +1,409 @@
+// src/html.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(not(feature = "uefi"))]
+use std::{string::String, vec::Vec};
+
+use crate::aozora::{group_into_words, split_sentences};
+use crate::model::{Content, Line, Segment, TypingCorrectnessChar, TypingCorrectnessContent, TypingCorrectnessSegment};
+use crate::ui;
+
+// --- HTMLからの読み込み ---
+
+/// 行の区切りとして扱うブロック要素
+const BLOCK_TAGS: [&str; 17] = [
+    "p", "div", "br", "li", "tr", "h1", "h2", "h3", "h4", "h5", "h6",
+    "section", "article", "blockquote", "ul", "ol", "table",
+];
+/// 中身を読み飛ばす要素
+const SKIPPED_TAGS: [&str; 4] = ["script", "style", "head", "rp"];
+
+/// 文字参照を1文字に変換する
+fn decode_entity(entity: &str) -> Option<char> {
+    match entity {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        _ => {
+            let code = if let Some(hex) = entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                u32::from_str_radix(hex, 16).ok()?
+            } else {
+                entity.strip_prefix('#')?.parse().ok()?
+            };
+            char::from_u32(code)
+        }
+    }
+}
+
+/// HTMLを読み進めながら、行ごとのセグメント列を組み立てる状態
+struct RubyHtmlReader {
+    lines: Vec<Vec<Segment>>,
+    title: Vec<Segment>,
+    current: Vec<Segment>,
+    plain: String,
+    ruby_base: String,
+    ruby_reading: String,
+    in_ruby: bool,
+    in_rt: bool,
+    in_title: bool,
+    skip_depth: usize,
+}
+
+impl RubyHtmlReader {
+    fn new() -> Self {
+        RubyHtmlReader {
+            lines: Vec::new(),
+            title: Vec::new(),
+            current: Vec::new(),
+            plain: String::new(),
+            ruby_base: String::new(),
+            ruby_reading: String::new(),
+            in_ruby: false,
+            in_rt: false,
+            in_title: false,
+            skip_depth: 0,
+        }
+    }
+
+    /// 地の文を1文字追加する。連続する空白は1つにまとめる
+    fn push_char(&mut self, c: char) {
+        if self.skip_depth > 0 && !self.in_title {
+            return;
+        }
+        let c = if c.is_whitespace() && c != '　' { ' ' } else { c };
+        let buffer = if self.in_rt {
+            &mut self.ruby_reading
+        } else if self.in_ruby {
+            &mut self.ruby_base
+        } else {
+            &mut self.plain
+        };
+        if c == ' ' && (buffer.is_empty() || buffer.ends_with(' ')) {
+            return;
+        }
+        buffer.push(c);
+    }
+
+    fn flush_plain(&mut self) {
+        if !self.plain.is_empty() {
+            self.current.push(Segment::Plain { text: core::mem::take(&mut self.plain) });
+        }
+    }
+
+    /// 現在の行を確定する
+    fn end_line(&mut self) {
+        self.flush_plain();
+        let mut segments = core::mem::take(&mut self.current);
+        trim_segments(&mut segments);
+        if segments.is_empty() {
+            return;
+        }
+        if self.in_title {
+            self.title = segments;
+        } else {
+            self.lines.push(segments);
+        }
+    }
+
+    fn handle_tag(&mut self, tag: &str) {
+        let is_closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        if name == "title" {
+            self.end_line();
+            self.in_title = !is_closing;
+            return;
+        }
+        if SKIPPED_TAGS.contains(&name.as_str()) {
+            if is_closing {
+                self.skip_depth = self.skip_depth.saturating_sub(1);
+            } else {
+                self.skip_depth += 1;
+            }
+            return;
+        }
+
+        match (name.as_str(), is_closing) {
+            ("ruby", false) => {
+                self.flush_plain();
+                self.in_ruby = true;
+            }
+            ("ruby", true) => {
+                // 読みが付かなかった親文字は地の文として扱う
+                self.in_ruby = false;
+                self.in_rt = false;
+                let rest = core::mem::take(&mut self.ruby_base);
+                self.plain.push_str(&rest);
+            }
+            ("rt", false) => self.in_rt = true,
+            ("rt", true) => {
+                self.in_rt = false;
+                let base = core::mem::take(&mut self.ruby_base);
+                let reading = core::mem::take(&mut self.ruby_reading);
+                self.flush_plain();
+                self.current.push(Segment::Annotated {
+                    base: String::from(base.trim()),
+                    reading: String::from(reading.trim()),
+                });
+            }
+            _ if BLOCK_TAGS.contains(&name.as_str()) => self.end_line(),
+            _ => {}
+        }
+    }
+}
+
+/// 行頭と行末の空白を取り除く
+fn trim_segments(segments: &mut Vec<Segment>) {
+    if let Some(Segment::Plain { text }) = segments.first_mut() {
+        *text = String::from(text.trim_start());
+    }
+    if let Some(Segment::Plain { text }) = segments.last_mut() {
+        *text = String::from(text.trim_end());
+    }
+    segments.retain(|segment| !matches!(segment, Segment::Plain { text } if text.is_empty()));
+}
+
+/// `<ruby>漢字<rt>かんじ</rt></ruby>` 形式のルビを含むHTMLを読み込み、`Content` に変換する
+///
+/// `<title>` を題名とし、ブロック要素ごとに行を区切ったうえで、さらに文末記号で1文ずつに分割する。
+/// `<rp>`、`<script>`、`<style>` の中身は無視する。それ以外のタグは取り除いて中身のテキストだけを使う。
+pub fn parse_ruby_html(input: &str) -> Content {
+    let chars: Vec<char> = input.chars().collect();
+    let mut reader = RubyHtmlReader::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        match chars[pos] {
+            '<' => {
+                let start = pos + 1;
+                // コメントは終端まで読み飛ばす
+                if chars[start..].starts_with(&['!', '-', '-']) {
+                    while pos < chars.len() && !(chars[pos] == '>' && pos >= 2 && chars[pos - 1] == '-' && chars[pos - 2] == '-') {
+                        pos += 1;
+                    }
+                } else {
+                    while pos < chars.len() && chars[pos] != '>' {
+                        pos += 1;
+                    }
+                    let tag: String = chars[start..pos.min(chars.len())].iter().collect();
+                    reader.handle_tag(&tag);
+                }
+            }
+            '&' => {
+                let end = chars[pos..].iter().take(10).position(|&c| c == ';').map(|offset| pos + offset);
+                let decoded = end.and_then(|end| decode_entity(&chars[pos + 1..end].iter().collect::<String>()));
+                match (end, decoded) {
+                    (Some(end), Some(c)) => {
+                        reader.push_char(c);
+                        pos = end;
+                    }
+                    _ => reader.push_char('&'),
+                }
+            }
+            c => reader.push_char(c),
+        }
+        pos += 1;
+    }
+    reader.end_line();
+
+    let title = Line { words: group_into_words(core::mem::take(&mut reader.title)), split: None };
+    let mut lines = Vec::new();
+    for segments in reader.lines {
+        for sentence in split_sentences(segments) {
+            let words = group_into_words(sentence);
+            if !words.is_empty() {
+                lines.push(Line { words, split: None });
+            }
+        }
+    }
+
+    Content { title, lines, options: Default::default() }
+}
+
+// --- HTMLへの書き出し ---
+
+/// 文字の入力状態に対応するCSSクラス名
+fn correctness_class(c: &TypingCorrectnessChar) -> &'static str {
+    match c {
+        TypingCorrectnessChar::Correct => "correct",
+        TypingCorrectnessChar::Incorrect => "incorrect",
+        TypingCorrectnessChar::Pending => "pending",
+    }
+}
+
+/// セグメント全体の入力状態。1文字でも間違えていれば誤り、全て入力済みなら正解とする
+fn segment_class(segment: &TypingCorrectnessSegment) -> &'static str {
+    if segment.chars.contains(&TypingCorrectnessChar::Incorrect) {
+        "incorrect"
+    } else if segment.chars.iter().all(|c| *c == TypingCorrectnessChar::Correct) {
+        "correct"
+    } else {
+        "pending"
+    }
+}
+
+fn push_escaped_html(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+}
+
+/// 各文字に入力状態のクラスを付けて出力する。同じクラスが続く文字は1つの要素にまとめる
+fn push_colored_chars(out: &mut String, text: &str, correctness: Option<&TypingCorrectnessSegment>) {
+    let Some(correctness) = correctness else {
+        push_escaped_html(out, text);
+        return;
+    };
+
+    let mut current_class: Option<&str> = None;
+    for (i, c) in text.chars().enumerate() {
+        let class = correctness.chars.get(i).map_or("pending", correctness_class);
+        if current_class != Some(class) {
+            if current_class.is_some() {
+                out.push_str("</span>");
+            }
+            out.push_str(&format!("<span class=\"{}\">", class));
+            current_class = Some(class);
+        }
+        let mut buffer = [0u8; 4];
+        push_escaped_html(out, c.encode_utf8(&mut buffer));
+    }
+    if current_class.is_some() {
+        out.push_str("</span>");
+    }
+}
+
+/// 1行をルビ付きのHTMLとして出力する
+fn push_line(out: &mut String, line: &Line, correctness: Option<&[Vec<&TypingCorrectnessSegment>]>) {
+    for (word_idx, word) in line.words.iter().enumerate() {
+        for (seg_idx, segment) in word.segments.iter().enumerate() {
+            let seg_correctness = correctness.and_then(|words| words.get(word_idx)).and_then(|segs| segs.get(seg_idx)).copied();
+            match segment {
+                Segment::Plain { text } => push_colored_chars(out, text, seg_correctness),
+                Segment::Annotated { base, reading } => {
+                    match seg_correctness {
+                        Some(seg) => out.push_str(&format!("<ruby class=\"{}\">", segment_class(seg))),
+                        None => out.push_str("<ruby>"),
+                    }
+                    push_escaped_html(out, base);
+                    out.push_str("<rp>(</rp><rt>");
+                    push_colored_chars(out, reading, seg_correctness);
+                    out.push_str("</rt><rp>)</rp></ruby>");
+                }
+            }
+        }
+    }
+}
+
+/// `0xAARRGGBB` 形式の色をCSSの色表記に変換する
+fn css_color(color: u32) -> String {
+    format!("#{:06X}", color & 0xFF_FFFF)
+}
+
+/// `Content` をルビ付きの単体HTMLとして書き出す
+///
+/// `correctness` を渡すと、終了したセッションの各文字の正誤をアプリと同じ色で塗り分ける。
+pub fn export_html(content: &Content, correctness: Option<&TypingCorrectnessContent>) -> String {
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n<title>");
+    push_escaped_html(&mut out, &content.title.to_string());
+    out.push_str("</title>\n<style>\n");
+    out.push_str("body { background: #101010; color: #FFFFFF; font-family: serif; line-height: 2.4; margin: 2em; }\n");
+    out.push_str("rt { font-size: 0.5em; }\n");
+    out.push_str(&format!(".correct {{ color: {}; }}\n", css_color(ui::CORRECT_COLOR)));
+    out.push_str(&format!(".incorrect {{ color: {}; }}\n", css_color(ui::INCORRECT_COLOR)));
+    out.push_str(&format!(".pending {{ color: {}; }}\n", css_color(ui::PENDING_COLOR)));
+    out.push_str("</style>\n</head>\n<body>\n<h1>");
+    push_line(&mut out, &content.title, None);
+    out.push_str("</h1>\n");
+
+    for (line_idx, line) in content.lines.iter().enumerate() {
+        // 行ごとの正誤情報を、単語・セグメントの添字で引けるように並べ替える
+        let line_correctness: Option<Vec<Vec<&TypingCorrectnessSegment>>> = correctness
+            .and_then(|c| c.lines.get(line_idx))
+            .map(|l| l.words.iter().map(|w| w.segments.iter().collect()).collect());
+        out.push_str("<p>");
+        push_line(&mut out, line, line_correctness.as_deref());
+        out.push_str("</p>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Word;
+    use crate::typing::create_typing_correctness_model;
+
+    fn plain(text: &str) -> Segment {
+        Segment::Plain { text: text.into() }
+    }
+
+    fn annotated(base: &str, reading: &str) -> Segment {
+        Segment::Annotated { base: base.into(), reading: reading.into() }
+    }
+
+    #[test]
+    fn test_parse_ruby_html() {
+        // ルビ、rp、エンティティ、ブロック要素による行分割を確認
+        let html = "<html><head><title>走れ<ruby>メロス<rt>めろす</rt></ruby></title><style>p { }</style></head>\n<body><p><ruby>吾輩<rp>(</rp><rt>わがはい</rt><rp>)</rp></ruby>は&amp;<ruby>猫<rt>ねこ</rt></ruby>である。</p><p>以上<br>終わり</p></body></html>";
+        let content = parse_ruby_html(html);
+        assert_eq!(content.title.to_string(), "走れメロス");
+        assert_eq!(content.lines.len(), 3);
+        assert_eq!(content.lines[0].words, vec![
+            Word { segments: vec![annotated("吾輩", "わがはい"), plain("は")] },
+            Word { segments: vec![plain("&")] },
+            Word { segments: vec![annotated("猫", "ねこ"), plain("である")] },
+            Word { segments: vec![plain("。")] },
+        ]);
+        assert_eq!(content.lines[1].to_string(), "以上");
+        assert_eq!(content.lines[2].to_string(), "終わり");
+    }
+
+    #[test]
+    fn test_parse_multiple_rt_in_one_ruby() {
+        // 1つのruby要素に複数の親文字と読みが含まれるケース
+        let content = parse_ruby_html("<p><ruby>漢<rt>かん</rt>字<rt>じ</rt></ruby></p>");
+        assert_eq!(content.lines[0].words, vec![
+            Word { segments: vec![annotated("漢", "かん")] },
+            Word { segments: vec![annotated("字", "じ")] },
+        ]);
+    }
+
+    #[test]
+    fn test_export_html_with_correctness() {
+        // 正誤情報に応じたクラスが付与されることを確認
+        let content = crate::parser::parse_problem("#title テスト\n(猫/ねこ)-だ");
+        let mut correctness = create_typing_correctness_model(&content);
+        correctness.lines[0].words[0].segments[0].chars[0] = TypingCorrectnessChar::Correct;
+        correctness.lines[0].words[0].segments[0].chars[1] = TypingCorrectnessChar::Incorrect;
+        let html = export_html(&content, Some(&correctness));
+        assert!(html.contains("<ruby class=\"incorrect\">猫<rp>(</rp><rt><span class=\"correct\">ね</span><span class=\"incorrect\">こ</span></rt>"));
+        assert!(html.contains("<span class=\"pending\">だ</span>"));
+        assert!(export_html(&content, None).contains("<ruby>猫<rp>(</rp><rt>ねこ</rt><rp>)</rp></ruby>だ"));
+    }
+}
@@ -0,0 +1,172 @@
+// src/karuta.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::vec::Vec;
+#[cfg(not(feature = "uefi"))]
+use std::vec::Vec;
+
+use crate::model::{Content, Line, TypingModel};
+use crate::typing;
+
+/// 上の句を見て、下の句を記憶から入力する練習の状態
+#[derive(Debug, Clone, PartialEq)]
+pub struct KarutaSession {
+    pub prompts: Vec<Line>, // 各行の上の句。時間制限で行を繰り返す場合も、同じ順で繰り返す
+    pub hint_line: i32, // ヒントを表示している行
+    pub revealed: usize, // `hint_line` で、ヒントにより行頭から表示したセグメントの数
+    pub hinted: Vec<(usize, usize)>, // セッションでヒントにより表示したセグメントの (行, 行頭からの位置)
+}
+
+/// 上の句と下の句の区切りがある行から、下の句を入力する問題を作る
+///
+/// 区切りのない行と、下の句が空の行は除く。そうした行しかない場合は `None` を返す。
+pub fn karuta_content(content: &Content) -> Option<(Content, KarutaSession)> {
+    let mut prompts = Vec::new();
+    let mut lines = Vec::new();
+    for line in &content.lines {
+        let Some(split) = line.split.filter(|&split| split < line.words.len()) else {
+            continue;
+        };
+        prompts.push(Line { words: line.words[..split].to_vec(), split: None });
+        lines.push(Line { words: line.words[split..].to_vec(), split: None });
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    let session = KarutaSession { prompts, hint_line: 0, revealed: 0, hinted: Vec::new() };
+    Some((Content { title: content.title.clone(), lines, options: content.options }, session))
+}
+
+/// 入力中の行で、入力を終えたセグメントの数（行頭から数える）
+fn typed_segments(model: &TypingModel) -> usize {
+    let Some(line) = model.content.lines.get(model.status.line as usize) else {
+        return 0;
+    };
+    let words: usize = line.words.iter().take(model.status.word as usize).map(|word| word.segments.len()).sum();
+    words + model.status.segment as usize
+}
+
+impl KarutaSession {
+    /// 指定した行の上の句
+    pub fn prompt(&self, line: i32) -> Option<&Line> {
+        if self.prompts.is_empty() {
+            return None;
+        }
+        self.prompts.get(line.max(0) as usize % self.prompts.len())
+    }
+
+    /// 入力中の行で表示するセグメントの数。入力を終えたセグメントと、ヒントで表示したセグメント
+    pub fn visible_segments(&self, model: &TypingModel) -> usize {
+        let typed = typed_segments(model);
+        if self.hint_line == model.status.line {
+            typed.max(self.revealed)
+        } else {
+            typed
+        }
+    }
+
+    /// 入力中の行の、まだ表示していない最初のセグメントを表示する。表示できるセグメントがなければ `false` を返す
+    pub fn reveal_next(&mut self, model: &TypingModel) -> bool {
+        let visible = self.visible_segments(model);
+        let total = model.content.lines.get(model.status.line as usize).map_or(0, |line| line.words.iter().map(|word| word.segments.len()).sum());
+        if visible >= total {
+            return false;
+        }
+        self.hint_line = model.status.line;
+        self.revealed = visible + 1;
+        self.hinted.push((model.status.line as usize, visible));
+        true
+    }
+
+    /// セッションで使ったヒントの回数
+    pub fn hints_used(&self) -> usize {
+        self.hinted.len()
+    }
+
+    /// ヒントなしで入力できたセグメントの数と、入力を終えたセグメントの数
+    ///
+    /// 入力を終えていないセグメントに使ったヒントは数えない。
+    pub fn score(&self, model: &TypingModel) -> (usize, usize) {
+        let mut completed = Vec::new(); // 行ごとの、入力を終えたセグメントの数
+        for (line_idx, word_idx) in typing::completed_word_positions(model) {
+            if completed.len() <= line_idx {
+                completed.resize(line_idx + 1, 0);
+            }
+            completed[line_idx] += model.content.lines[line_idx].words[word_idx].segments.len();
+        }
+        let total: usize = completed.iter().sum();
+        let hinted = self.hinted.iter().filter(|&&(line, segment)| completed.get(line).is_some_and(|&count| segment < count)).count();
+        (total - hinted, total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Layout, Model};
+    use crate::parser::parse_problem;
+    use crate::typing::tests::{every_100ms, new_model_with_layout, type_keys};
+
+    /// かるたの問題で入力を始めたモデル
+    fn karuta_model(content: &Content) -> TypingModel {
+        let mut model = new_model_with_layout("#title k\nかな\n", Layout::default());
+        model.typing_correctness = typing::create_typing_correctness_model(content);
+        model.content = content.clone();
+        model
+    }
+
+    #[test]
+    fn test_karuta_content() {
+        // 区切りのない行と下の句が空の行を除いて、下の句だけの問題になることを確認
+        let content = parse_problem("#title k\n(秋/あき)の|(露/つゆ)に/ぬれ\nかな\nあき|\nいろ|は\n");
+        let (karuta, session) = karuta_content(&content).unwrap();
+        assert_eq!(karuta.lines.iter().map(|line| line.to_string()).collect::<Vec<_>>(), ["露にぬれ", "は"]);
+        assert_eq!(session.prompts.iter().map(|line| line.to_string()).collect::<Vec<_>>(), ["秋の", "いろ"]);
+        assert!(karuta_content(&parse_problem("#title k\nかな\nあき|\n")).is_none());
+    }
+
+    #[test]
+    fn test_prompt_wraps_on_laps() {
+        // 時間制限で行を繰り返す場合も、上の句が同じ順で繰り返されることを確認
+        let (_, session) = karuta_content(&parse_problem("#title k\nあ|い\nう|え\n")).unwrap();
+        assert_eq!(session.prompt(0).unwrap().to_string(), "あ");
+        assert_eq!(session.prompt(1).unwrap().to_string(), "う");
+        assert_eq!(session.prompt(2).unwrap().to_string(), "あ");
+        assert_eq!(session.prompt(5).unwrap().to_string(), "う");
+        let empty = KarutaSession { prompts: Vec::new(), hint_line: 0, revealed: 0, hinted: Vec::new() };
+        assert!(empty.prompt(0).is_none());
+    }
+
+    #[test]
+    fn test_karuta_hints() {
+        // ヒントが1セグメントずつ表示され、ヒントを使ったセグメントが得点から除かれることを確認
+        let content = parse_problem("#title k\n(秋/あき)の|(露/つゆ)に/ぬれ\n");
+        let (karuta, mut session) = karuta_content(&content).unwrap();
+        let model = karuta_model(&karuta);
+        assert!(session.reveal_next(&model));
+        assert!(session.reveal_next(&model));
+        assert_eq!(session.visible_segments(&model), 2);
+        assert!(session.reveal_next(&model));
+        assert!(!session.reveal_next(&model));
+        assert_eq!(session.hints_used(), 3);
+
+        let Model::Result(result) = type_keys(model, "tuyuninure", every_100ms()) else { panic!("session should be completed") };
+        assert_eq!(session.score(&result.typing_model), (0, 3));
+    }
+
+    #[test]
+    fn test_score_with_hint_on_unfinished_line() {
+        // 入力を終えていない行で使ったヒントは、回数には含めても得点からは除かないことを確認
+        let content = parse_problem("#title k\nあ|(露/つゆ)/に\nい|なみ\n");
+        let (karuta, mut session) = karuta_content(&content).unwrap();
+        let model = karuta_model(&karuta);
+        assert!(session.reveal_next(&model));
+        let Model::Typing(model) = type_keys(model, "tuyuni", every_100ms()) else { panic!("session should continue") };
+        assert!(session.reveal_next(&model));
+        assert_eq!(session.hints_used(), 2);
+        assert_eq!(session.score(&model), (1, 2));
+    }
+}
@@ -4,7 +4,7 @@
 extern crate alloc;
 
 #[cfg(feature = "uefi")]
-use alloc::{string::String, vec::Vec};
+use alloc::{string::String, vec, vec::Vec};
 #[cfg(not(feature = "uefi"))]
 use std::{string::String, vec::Vec};
 
@@ -12,6 +12,7 @@ use phf::phf_map;
 
 // PHF doesn't support Vec<String> directly in its static map.
 // We define it as a static map of string slices and convert it at runtime.
+// Keys are written in hiragana only; katakana readings are converted to hiragana before lookup.
 type PhfLayoutMap = phf::Map<&'static str, &'static [&'static str]>;
 
 static JAPANESE_LAYOUT: PhfLayoutMap = phf_map! {
@@ -21,6 +22,7 @@ static JAPANESE_LAYOUT: PhfLayoutMap = phf_map! {
     "か" => &["ka", "ca"], "き" => &["ki"], "く" => &["ku", "cu", "qu"], "け" => &["ke"], "こ" => &["ko", "co"],
     "きゃ" => &["kya"], "きぃ" => &["kyi"], "きゅ" => &["kyu"], "きぇ" => &["kye"], "きょ" => &["kyo"],
     "くゃ" => &["qya"], "くゅ" => &["qyu"], "くょ" => &["qyo"],
+    "ゕ" => &["lka", "xka"], "ゖ" => &["lke", "xke"],
     "くぁ" => &["qwa", "qa", "kwa"], "くぃ" => &["qwi", "qi", "qyi"], "くぅ" => &["qwu"], "くぇ" => &["qwe", "qe", "qye"], "くぉ" => &["qwo", "qo"],
     "が" => &["ga"], "ぎ" => &["gi"], "ぐ" => &["gu"], "げ" => &["ge"], "ご" => &["go"],
     "ぎゃ" => &["gya"], "ぎぃ" => &["gyi"], "ぎゅ" => &["gyu"], "ぎぇ" => &["gye"], "ぎょ" => &["gyo"],
@@ -47,7 +49,7 @@ static JAPANESE_LAYOUT: PhfLayoutMap = phf_map! {
     "ふぁ" => &["fwa", "fa"], "ふぃ" => &["fwi", "fi", "fyi"], "ふぅ" => &["fwu"], "ふぇ" => &["fwe", "fe", "fye"], "ふぉ" => &["fwo", "fo"],
     "ば" => &["ba"], "び" => &["bi"], "ぶ" => &["bu"], "べ" => &["be"], "ぼ" => &["bo"],
     "びゃ" => &["bya"], "びぃ" => &["byi"], "びゅ" => &["byu"], "びぇ" => &["bye"], "びょ" => &["byo"],
-    "ヴぁ" => &["va"], "ヴぃ" => &["vyi","vi"], "ヴぇ" => &["vye"], "ヴぉ" => &["vo"], "ヴゃ" => &["vya"], "ヴゅ" => &["vyu"], "ヴょ" => &["vyo"],
+    "ゔぁ" => &["va"], "ゔぃ" => &["vyi","vi"], "ゔ" => &["vu"], "ゔぇ" => &["vye"], "ゔぉ" => &["vo"], "ゔゃ" => &["vya"], "ゔゅ" => &["vyu"], "ゔょ" => &["vyo"],
     "ぱ" => &["pa"], "ぴ" => &["pi"], "ぷ" => &["pu"], "ぺ" => &["pe"], "ぽ" => &["po"],
     "ぴゃ" => &["pya"], "ぴぃ" => &["pyi"], "ぴゅ" => &["pyu"], "ぴぇ" => &["pye"], "ぴょ" => &["pyo"],
     "ま" => &["ma"], "み" => &["mi"], "む" => &["mu"], "め" => &["me"], "も" => &["mo"],
@@ -62,7 +64,7 @@ static JAPANESE_LAYOUT: PhfLayoutMap = phf_map! {
     "っう" => &["wwu","wwhu"], "っい" => &["yyi"], "っいぇ" => &["yye"], "っうぁ" => &["wwha"], "っうぃ" => &["wwhi"], "っうぇ" => &["wwhe"], "っうぉ" => &["wwho"],
     "っくぁ" => &["qqa","qqwa", "qkwa"], "っくぃ" => &["qqi","qqwi","qqyi"], "っくぇ" => &["qqe","qqwe","kkya"], "っくぅ" => &["qqwu"], "っくぉ" => &["qqo","qqwo"],
     "っか" => &["kka","cca"], "っき" => &["kki"], "っく" => &["kku","ccu","qqu"], "っけ" => &["kke"], "っこ" => &["kko","cco"],
-    "っヵ" => &["llka","xxka"], "っヶ" => &["xxke","xxke"],
+    "っゕ" => &["llka","xxka"], "っゖ" => &["llke","xxke"],
     "っきゃ" => &["kkya"], "っきぃ" => &["kkyi"], "っきゅ" => &["kkyu"], "っきぇ" => &["kkye"], "っきょ" => &["kkyo"],
     "っくゃ" => &["kkya"], "っくゅ" => &["kkya","qqyu"], "っくょ" => &["kkya"],
     "っが" => &["gga"], "っぎ" => &["ggi"], "っぐ" => &["ggu"], "っげ" => &["gge"], "っご" => &["ggo"],
@@ -89,8 +91,8 @@ static JAPANESE_LAYOUT: PhfLayoutMap = phf_map! {
     "っふぁ" => &["ffwa", "ffa"], "っふぃ" => &["ffwi", "ffi", "ffyi"], "っふぅ" => &["ffwu"], "っふぇ" => &["ffwe", "ffe", "ffye"], "っふぉ" => &["ffwo", "ffo"],
     "っば" => &["bba"], "っび" => &["bbi"], "っぶ" => &["bbu"], "っべ" => &["bbe"], "っぼ" => &["bbo"],
     "っびゃ" => &["bya"], "っびぃ" => &["byi"], "っびゅ" => &["byu"], "っびぇ" => &["bye"], "っびょ" => &["byo"],
-    "っヴぁ" => &["vva"], "っヴぃ" => &["vvi","vvyi"], "っヴ" => &["vvu"], "っヴぇ" => &["vvye"], "っヴぉ" => &["vvo"],
-    "っヴゃ" => &["vvya"], "っヴゅ" => &["vvyu"], "っヴょ" => &["vvyo"],
+    "っゔぁ" => &["vva"], "っゔぃ" => &["vvi","vvyi"], "っゔ" => &["vvu"], "っゔぇ" => &["vvye"], "っゔぉ" => &["vvo"],
+    "っゔゃ" => &["vvya"], "っゔゅ" => &["vvyu"], "っゔょ" => &["vvyo"],
     "っぱ" => &["ppa"], "っぴ" => &["ppi"], "っぷ" => &["ppu"], "っぺ" => &["ppe"], "っぽ" => &["ppo"],
     "っぴゃ" => &["ppya"], "っぴぃ" => &["ppyi"], "っぴゅ" => &["ppyu"], "っぴぇ" => &["ppye"], "っぴょ" => &["ppyo"],
     "っま" => &["mma"], "っみ" => &["mmi"], "っむ" => &["mmu"], "っめ" => &["mme"], "っも" => &["mmo"],
@@ -101,95 +103,6 @@ static JAPANESE_LAYOUT: PhfLayoutMap = phf_map! {
     "っりゃ" => &["rrya"], "っりぃ" => &["rryi"], "っりゅ" => &["rryu"], "っりぇ" => &["rrye"], "っりょ" => &["rryo"],
     "っわ" => &["wwa"], "っゐ" => &["wwyi"], "っゑ" => &["wwye"], "っを" => &["wwo"],
     "っん" => &["xxn"], "っゎ" => &["llwa", "xxwa"],
-    "ア" => &["a"], "イ" => &["i", "yi"], "ウ" => &["u", "wu", "whu"], "エ" => &["e"], "オ" => &["o"],
-    "ァ" => &["la", "xa"], "ィ" => &["li", "xi", "lyi", "xyi"], "ゥ" => &["lu", "xu"], "ェ" => &["le", "xe", "lye", "xye"], "ォ" => &["lo", "xo"],
-    "イェ" => &["ye"], "ウァ" => &["wha"], "ウィ" => &["whi", "wi"], "ウェ" => &["whe", "we"], "ウォ" => &["who"],
-    "カ" => &["ka", "ca"], "キ" => &["ki"], "ク" => &["ku", "cu", "qu"], "ケ" => &["ke"], "コ" => &["ko", "co"],
-    "キャ" => &["kya"], "キィ" => &["kyi"], "キュ" => &["kyu"], "キェ" => &["kye"], "キョ" => &["kyo"],
-    "クャ" => &["qya"], "クュ" => &["qyu"], "クョ" => &["qyo"],
-    "ヵ" => &["lka", "xka"], "ヶ" => &["lke", "xke"],
-    "クァ" => &["qwa", "qa", "kwa"], "クィ" => &["qwi", "qi", "qyi"], "クゥ" => &["qwu"], "クェ" => &["qwe", "qe", "qye"], "クォ" => &["qwo", "qo"],
-    "ガ" => &["ga"], "ギ" => &["gi"], "グ" => &["gu"], "ゲ" => &["ge"], "ゴ" => &["go"],
-    "ギャ" => &["gya"], "ギィ" => &["gyi"], "ギュ" => &["gyu"], "ギェ" => &["gye"], "ギョ" => &["gyo"],
-    "グァ" => &["gwa"], "グィ" => &["gwi"], "グゥ" => &["gwu"], "グェ" => &["gwe"], "グォ" => &["gwo"],
-    "サ" => &["sa"], "シ" => &["si", "ci", "shi"], "ス" => &["su"], "セ" => &["se", "ce"], "ソ" => &["so"],
-    "シャ" => &["sya", "sha"], "シィ" => &["syi"], "シュ" => &["syu", "shu"], "シェ" => &["sye", "she"], "ショ" => &["syo", "sho"],
-    "スァ" => &["swa"], "スィ" => &["swi"], "スゥ" => &["swu"], "スェ" => &["swe"], "スォ" => &["swo"],
-    "ザ" => &["za"], "ジ" => &["zi", "ji"], "ズ" => &["zu"], "ゼ" => &["ze"], "ゾ" => &["zo"],
-    "ジャ" => &["zya", "ja", "jya"], "ジィ" => &["zyi", "jyi"], "ジュ" => &["zyu", "ju", "jyu"], "ジェ" => &["zye", "je", "jye"], "ジョ" => &["zyo", "jo", "jyo"],
-    "タ" => &["ta"], "チ" => &["ti", "chi"], "ツ" => &["tu", "tsu"], "テ" => &["te"], "ト" => &["to"],
-    "チャ" => &["tya", "cha", "cya"], "チィ" => &["tyi", "", "cyi"], "チュ" => &["tyu", "chu", "cyu"], "チェ" => &["tye", "che", "cye"], "チョ" => &["tyo", "cho", "cyo"],
-    "ッ" => &["ltu", "xtu", "ltsu", "xtsu"], "ツァ" => &["tsa"], "ツィ" => &["tsi"], "ツェ" => &["tse"], "ツォ" => &["tso"],
-    "テャ" => &["tha"], "ティ" => &["thi"], "テュ" => &["thu"], "テェ" => &["the"], "テョ" => &["tho"],
-    "トァ" => &["twa"], "トィ" => &["twi"], "トゥ" => &["twu"], "トェ" => &["twe"], "トォ" => &["two"],
-    "ダ" => &["da"], "ヂ" => &["di"], "ヅ" => &["du"], "デ" => &["de"], "ド" => &["do"],
-    "ヂャ" => &["dya"], "ヂィ" => &["dyi"], "ヂュ" => &["dyu"], "ヂェ" => &["dye"], "ヂョ" => &["dyo"],
-    "デャ" => &["dha"], "ディ" => &["dhi"], "デュ" => &["dhu"], "デェ" => &["dhe"], "デョ" => &["dho"],
-    "ドァ" => &["dwa"], "ドィ" => &["dwi"], "ドゥ" => &["dwu"], "ドェ" => &["dwe"], "ドォ" => &["dwo"],
-    "ナ" => &["na"], "ニ" => &["ni"], "ヌ" => &["nu"], "ネ" => &["ne"], "ノ" => &["no"],
-    "ニャ" => &["nya"], "ニィ" => &["nyi"], "ニュ" => &["nyu"], "ニェ" => &["nye"], "ニョ" => &["nyo"],
-    "ハ" => &["ha"], "ヒ" => &["hi"], "フ" => &["hu", "fu"], "ヘ" => &["he"], "ホ" => &["ho"],
-    "ヒャ" => &["hya"], "ヒィ" => &["hyi"], "ヒュ" => &["hyu"], "ヒェ" => &["hye"], "ヒョ" => &["hyo"],
-    "フャ" => &["fya"], "フュ" => &["fyu"], "フョ" => &["fyo"],
-    "ファ" => &["fwa", "fa"], "フィ" => &["fwi", "fi", "fyi"], "フゥ" => &["fwu"], "フェ" => &["fwe", "fe", "fye"], "フォ" => &["fwo", "fo"],
-    "バ" => &["ba"], "ビ" => &["bi"], "ブ" => &["bu"], "ベ" => &["be"], "ボ" => &["bo"],
-    "ビャ" => &["bya"], "ビィ" => &["byi"], "ビュ" => &["byu"], "ビェ" => &["bye"], "ビョ" => &["byo"],
-    "ヴァ" => &["va"], "ヴィ" => &["vyi","vi"], "ヴ" => &["vu"], "ヴェ" => &["vye"], "ヴォ" => &["vo"],
-    "ヴャ" => &["vya"], "ヴュ" => &["vyu"], "ヴョ" => &["vyo"],
-    "パ" => &["pa"], "ピ" => &["pi"], "プ" => &["pu"], "ペ" => &["pe"], "ポ" => &["po"],
-    "ピャ" => &["pya"], "ピィ" => &["pyi"], "ピュ" => &["pyu"], "ピェ" => &["pye"], "ピョ" => &["pyo"],
-    "マ" => &["ma"], "ミ" => &["mi"], "ム" => &["mu"], "メ" => &["me"], "モ" => &["mo"],
-    "ミャ" => &["mya"], "ミィ" => &["myi"], "ミュ" => &["myu"], "ミェ" => &["mye"], "ミョ" => &["myo"],
-    "ヤ" => &["ya"], "ユ" => &["yu"], "ヨ" => &["yo"],
-    "ャ" => &["lya", "xya"], "ュ" => &["lyu", "xyu"], "ョ" => &["lyo", "xyo"],
-    "ラ" => &["ra"], "リ" => &["ri"], "ル" => &["ru"], "レ" => &["re"], "ロ" => &["ro"],
-    "リャ" => &["rya"], "リィ" => &["ryi"], "リュ" => &["ryu"], "リェ" => &["rye"], "リョ" => &["ryo"],
-    "ワ" => &["wa"], "ヰ" => &["wyi"], "ヱ" => &["wye"], "ヲ" => &["wo"],
-    "ン" => &["nn", "n'", "xn"], "ヮ" => &["lwa", "xwa"],
-    "ッァ" => &["lla","xxa"], "ッィ" => &["lli","xxi"], "ッゥ" => &["llu","xxu"], "ッェ" => &["lle","xxe"], "ッォ" => &["llo","xxo"],
-    "ッウ" => &["wwu","wwhu"], "ッイ" => &["yyi"], "ッイェ" => &["yye"],
-    "ッウァ" => &["wwha"], "ッウィ" => &["wwhi"], "ッウェ" => &["wwhe"], "ッウォ" => &["wwho"],
-    "ックァ" => &["qqa","qqwa", "qkwa"], "ックィ" => &["qqi","qqwi","qqyi"], "ックェ" => &["qqe","qqwe","kkya"], "ックゥ" => &["qqwu"], "ックォ" => &["qqo","qqwo"],
-    "ッカ" => &["kka","cca"], "ッキ" => &["kki"], "ック" => &["kku","ccu","qqu"], "ッケ" => &["kke"], "ッコ" => &["kko","cco"],
-    "ッヵ" => &["llka","xxka"], "ッヶ" => &["xxke","xxke"],
-    "ッキャ" => &["kkya"], "ッキィ" => &["kkyi"], "ッキュ" => &["kkyu"], "ッキェ" => &["kkye"], "ッキョ" => &["kkyo"],
-    "ックャ" => &["kkya"], "ックュ" => &["kkya","qqyu"], "ックョ" => &["kkya"],
-    "ッガ" => &["gga"], "ッギ" => &["ggi"], "ッグ" => &["ggu"], "ッゲ" => &["gge"], "ッゴ" => &["ggo"],
-    "ッギャ" => &["ggya"], "ッギィ" => &["ggyi"], "ッギュ" => &["ggyu"], "ッギェ" => &["ggye"], "ッギョ" => &["ggyo"],
-    "ッグァ" => &["ggwa"], "ッグィ" => &["gwi"], "ッグゥ" => &["gwu"], "ッグェ" => &["gwe"], "ッグォ" => &["gwo"],
-    "ッサ" => &["ssa"], "ッシ" => &["ssi","cci","sshi"], "ッス" => &["ssu"], "ッセ" => &["sse","cce"], "ッソ" => &["sso"],
-    "ッザ" => &["zza"], "ッジ" => &["zzi","jji"], "ッズ" => &["zzu"], "ッゼ" => &["zze"], "ッゾ" => &["zzo"],
-    "ッシャ" => &["ssya","ssha"], "ッシィ" => &["ssyi","sshi"], "ッシュ" => &["ssyu","sshu"], "ッシェ" => &["ssye","sshe"], "ッシォ" => &["ssyo","ssho"],
-    "ッスァ" => &["sswa"], "ッスゥ" => &["sswu"], "ッスェ" => &["sswe"], "ッスォ" => &["swo"],
-    "ッジァ" => &["zzya","jjya"], "ッジィ" => &["zzyi","jjyi"], "ッジャ" => &["jja","zzya","jjya"], "ッジュ" => &["jju","zzyu","jjyu"], "ッジェ" => &["jje","zzye","jjye"], "ッジョ" => &["jjo","zzyo","jjyo"],
-    "ッタ" => &["tta"], "ッチ" => &["tti","cchi"], "ッツ" => &["ttu","ttsu"], "ッテ" => &["tte"], "ット" => &["tto"],
-    "ッチャ" => &["ttya","ccya","ccha"], "ッチィ" => &["ttyi","ccyi","cchi"], "ッチュ" => &["ttyu","ccyu","cchu"], "ッチェ" => &["ttye","ccye","cche"], "ッチョ" => &["ttyo","ccyo","ccho"],
-    "ッッ" => &["lltu", "xxtu"],
-    "ッツァ" => &["ttsa"], "ッツィ" => &["ttsi"], "ッツェ" => &["tse"], "ッツォ" => &["tso"],
-    "ッテャ" => &["ttha"], "ッティ" => &["tthi"], "ッテュ" => &["tthu"], "ッテェ" => &["tthe"], "ッテョ" => &["ttho"],
-    "ットァ" => &["ttwa"], "ットィ" => &["twi"], "ットゥ" => &["ttwu"], "ットェ" => &["ttwe"], "ットォ" => &["ttwo"],
-    "ッダ" => &["dda"], "ッヂ" => &["ddi"], "ッヅ" => &["ddu"], "ッデ" => &["dde"], "ッド" => &["ddo"],
-    "ッヂャ" => &["dya"], "ッヂィ" => &["dyi"], "ッヂュ" => &["dyu"], "ッヂェ" => &["dye"], "ッヂョ" => &["dyo"],
-    "ッデャ" => &["dha"], "ッディ" => &["dhi"], "ッデュ" => &["dhu"], "ッデェ" => &["dhe"], "ッデョ" => &["dho"],
-    "ッドァ" => &["ddwa"], "ッドィ" => &["dwi"], "ッドゥ" => &["ddwu"], "ッドェ" => &["dwe"], "ッドォ" => &["dwo"],
-    "ッハ" => &["hha"], "ッヒ" => &["hhi"], "ッフ" => &["hhu","ffu"], "ッヘ" => &["hhe"], "ッホ" => &["hho"],
-    "ッヒャ" => &["hhya"], "ッヒィ" => &["hhyi"], "ッヒュ" => &["hhyu"], "ッヒェ" => &["hhye"], "ッヒョ" => &["hhyo"],
-    "ッフャ" => &["ffya"], "ッフュ" => &["ffyu"], "ッフョ" => &["ffyo"],
-    "ッファ" => &["ffwa", "ffa"], "ッフィ" => &["ffwi", "ffi", "ffyi"], "ッフゥ" => &["ffwu"], "ッフェ" => &["ffwe", "ffe", "ffye"], "ッフォ" => &["ffwo", "ffo"],
-    "ッバ" => &["bba"], "ッビ" => &["bbi"], "ッブ" => &["bbu"], "ッベ" => &["bbe"], "ッボ" => &["bbo"],
-    "ッビャ" => &["bya"], "ッビィ" => &["byi"], "ッビュ" => &["byu"], "ッビェ" => &["bye"], "ッビョ" => &["byo"],
-    "ッヴァ" => &["vva"], "ッヴィ" => &["vvi","vvyi"], "ッヴ" => &["vvu"], "ッヴェ" => &["vvye"], "ッヴォ" => &["vvo"],
-    "ッヴャ" => &["vvya"], "ッヴュ" => &["vvyu"], "ッヴョ" => &["vvyo"],
-    "ッパ" => &["ppa"], "ッピ" => &["ppi"], "ップ" => &["ppu"], "ッペ" => &["ppe"], "ッポ" => &["ppo"],
-    "ッピャ" => &["ppya"], "ッピィ" => &["ppyi"], "ッピュ" => &["ppyu"], "ッピェ" => &["ppye"], "ッピョ" => &["ppyo"],
-    "ッマ" => &["mma"], "ッミ" => &["mmi"], "ッム" => &["mmu"], "ッメ" => &["mme"], "ッモ" => &["mmo"],
-    "ッミャ" => &["mmya"], "ッミィ" => &["mmyi"], "ッミュ" => &["mmyu"], "ッミェ" => &["mmye"], "ッミョ" => &["mmyo"],
-    "ッヤ" => &["yya"], "ッユ" => &["yyu"], "ッヨ" => &["yyo"],
-    "ッャ" => &["llya", "xxya"], "ッュ" => &["llyu", "xxyu"], "ッョ" => &["llyo", "xxyo"],
-    "ッラ" => &["rra"], "ッリ" => &["rri"], "ッル" => &["rru"], "ッレ" => &["rre"], "ッロ" => &["rro"],
-    "ッリャ" => &["rrya"], "ッリィ" => &["rryi"], "ッリュ" => &["rryu"], "ッリェ" => &["rrye"], "ッリョ" => &["rryo"],
-    "ッワ" => &["wwa"], "ッヰ" => &["wwyi"], "ッヱ" => &["wwye"], "ッヲ" => &["wwo"],
-    "ッン" => &["xxn"], "ッヮ" => &["llwa", "xxwa"],
     "a" => &["a"], "b" => &["b"], "c" => &["c"], "d" => &["d"], "e" => &["e"], "f" => &["f"], "g" => &["g"], "h" => &["h"], "i" => &["i"], "j" => &["j"], "k" => &["k"], "l" => &["l"], "m" => &["m"], "n" => &["n"], "o" => &["o"], "p" => &["p"], "q" => &["q"], "r" => &["r"], "s" => &["s"], "t" => &["t"], "u" => &["u"], "v" => &["v"], "w" => &["w"], "x" => &["x"], "y" => &["y"], "z" => &["z"],
     "A" => &["A"], "B" => &["B"], "C" => &["C"], "D" => &["D"], "E" => &["E"], "F" => &["F"], "G" => &["G"], "H" => &["H"], "I" => &["I"], "J" => &["J"], "K" => &["K"], "L" => &["L"], "M" => &["M"], "N" => &["N"], "O" => &["O"], "P" => &["P"], "Q" => &["Q"], "R" => &["R"], "S" => &["S"], "T" => &["T"], "U" => &["U"], "V" => &["V"], "W" => &["W"], "X" => &["X"], "Y" => &["Y"], "Z" => &["Z"],
     "1" => &["1"], "2" => &["2"], "3" => &["3"], "4" => &["4"], "5" => &["5"], "6" => &["6"], "7" => &["7"], "8" => &["8"], "9" => &["9"], "0" => &["0"],
@@ -211,4 +124,317 @@ pub fn get_layout() -> Vec<(String, Vec<String>)> {
             )
         })
         .collect()
-}
\ No newline at end of file
+}
+/// Adds a romaji spelling for a kana, creating the entry if needed.
+pub fn add_entry(mapping: &mut Vec<(String, Vec<String>)>, kana: &str, romaji: &str) {
+    match mapping.iter_mut().find(|(key, _)| key == kana) {
+        Some((_, values)) => {
+            if !values.iter().any(|value| value == romaji) {
+                values.push(String::from(romaji));
+            }
+        }
+        None => mapping.push((String::from(kana), vec![String::from(romaji)])),
+    }
+}
+
+/// Consonant rows that take the AZIK extensions, with the kana of each row for the vowels a, i, u, e, o.
+const AZIK_ROWS: [(&str, [&str; 5]); 26] = [
+    ("k", ["か", "き", "く", "け", "こ"]),
+    ("s", ["さ", "し", "す", "せ", "そ"]),
+    ("t", ["た", "ち", "つ", "て", "と"]),
+    ("n", ["な", "に", "ぬ", "ね", "の"]),
+    ("h", ["は", "ひ", "ふ", "へ", "ほ"]),
+    ("m", ["ま", "み", "む", "め", "も"]),
+    ("y", ["や", "い", "ゆ", "いぇ", "よ"]),
+    ("r", ["ら", "り", "る", "れ", "ろ"]),
+    ("w", ["わ", "うぃ", "う", "うぇ", "を"]),
+    ("g", ["が", "ぎ", "ぐ", "げ", "ご"]),
+    ("z", ["ざ", "じ", "ず", "ぜ", "ぞ"]),
+    ("d", ["だ", "ぢ", "づ", "で", "ど"]),
+    ("b", ["ば", "び", "ぶ", "べ", "ぼ"]),
+    ("p", ["ぱ", "ぴ", "ぷ", "ぺ", "ぽ"]),
+    ("f", ["ふぁ", "ふぃ", "ふ", "ふぇ", "ふぉ"]),
+    ("j", ["じゃ", "じ", "じゅ", "じぇ", "じょ"]),
+    ("ky", ["きゃ", "きぃ", "きゅ", "きぇ", "きょ"]),
+    ("gy", ["ぎゃ", "ぎぃ", "ぎゅ", "ぎぇ", "ぎょ"]),
+    ("sy", ["しゃ", "しぃ", "しゅ", "しぇ", "しょ"]),
+    ("zy", ["じゃ", "じぃ", "じゅ", "じぇ", "じょ"]),
+    ("ty", ["ちゃ", "ちぃ", "ちゅ", "ちぇ", "ちょ"]),
+    ("ny", ["にゃ", "にぃ", "にゅ", "にぇ", "にょ"]),
+    ("hy", ["ひゃ", "ひぃ", "ひゅ", "ひぇ", "ひょ"]),
+    ("my", ["みゃ", "みぃ", "みゅ", "みぇ", "みょ"]),
+    ("ry", ["りゃ", "りぃ", "りゅ", "りぇ", "りょ"]),
+    ("by", ["びゃ", "びぃ", "びゅ", "びぇ", "びょ"]),
+];
+
+/// AZIK extension keys: (key, index of the vowel in the row it replaces, kana appended after that vowel).
+/// The first five are the nasal extension (`kz` → かん), the rest the double vowel extension (`kp` → こう).
+const AZIK_EXTENSIONS: [(char, usize, &str); 9] = [
+    ('z', 0, "ん"), ('k', 1, "ん"), ('j', 2, "ん"), ('d', 3, "ん"), ('l', 4, "ん"),
+    ('q', 0, "い"), ('h', 2, "う"), ('w', 3, "い"), ('p', 4, "う"),
+];
+
+/// Consonant rows that ACT adds on top of the standard table: か can also be typed with `c`.
+const ACT_ROWS: [(&str, [&str; 5]); 1] = [
+    ("c", ["か", "き", "く", "け", "こ"]),
+];
+
+/// ACT extension keys, in the same form as `AZIK_EXTENSIONS`.
+/// On a Dvorak keyboard the nasal keys sit below each vowel (`c;` → かん)
+/// and the double vowel keys above it (`c,` → こう).
+const ACT_EXTENSIONS: [(char, usize, &str); 9] = [
+    (';', 0, "ん"), ('x', 1, "ん"), ('k', 2, "ん"), ('j', 3, "ん"), ('q', 4, "ん"),
+    ('\'', 0, "い"), ('p', 2, "う"), ('.', 3, "い"), (',', 4, "う"),
+];
+
+/// Adds `row + key` for every row and extension key.
+fn add_extensions(mapping: &mut Vec<(String, Vec<String>)>, rows: &[(&str, [&str; 5])], extensions: &[(char, usize, &str)]) {
+    for (row, kana) in rows {
+        for (key, vowel, suffix) in extensions {
+            let mut romaji = String::from(*row);
+            romaji.push(*key);
+            add_entry(mapping, &(String::from(kana[*vowel]) + suffix), &romaji);
+        }
+    }
+}
+
+/// Builds the AZIK layout: the standard table plus the nasal and double vowel
+/// extensions, `q` for ん and `;` for っ.
+/// Standard spellings stay valid because matching only considers entries for the expected kana.
+pub fn get_azik_layout() -> Vec<(String, Vec<String>)> {
+    let mut mapping = get_layout();
+    add_extensions(&mut mapping, &AZIK_ROWS, &AZIK_EXTENSIONS);
+    add_entry(&mut mapping, "ん", "q");
+    add_entry(&mut mapping, "っ", ";");
+    mapping
+}
+
+/// Builds the ACT layout: the standard table plus the `c` row for か and
+/// the Dvorak-placed nasal and double vowel extensions on every row.
+pub fn get_act_layout() -> Vec<(String, Vec<String>)> {
+    let mut mapping = get_layout();
+    for (row, kana) in ACT_ROWS {
+        for (vowel, kana) in "aiueo".chars().zip(kana) {
+            let mut romaji = String::from(row);
+            romaji.push(vowel);
+            add_entry(&mut mapping, kana, &romaji);
+        }
+    }
+    add_extensions(&mut mapping, &AZIK_ROWS, &ACT_EXTENSIONS);
+    add_extensions(&mut mapping, &ACT_ROWS, &ACT_EXTENSIONS);
+    mapping
+}
+
+/// Removes the leading doubled consonant of a sokuon spelling (`sshi` → `shi`).
+fn strip_sokuon(romaji: &str) -> &str {
+    let mut chars = romaji.chars();
+    match (chars.next(), chars.next()) {
+        (Some(a), Some(b)) if a == b && !"aiueon".contains(a) => &romaji[1..],
+        _ => romaji,
+    }
+}
+
+/// Spellings that only exist in Hepburn romanization.
+pub fn is_hepburn_spelling(romaji: &str) -> bool {
+    let core = strip_sokuon(romaji);
+    matches!(core, "tsu" | "fu") || ["sh", "ch", "j"].iter().any(|prefix| core.starts_with(prefix))
+}
+
+/// Spellings that only exist in Kunrei-shiki romanization.
+pub fn is_kunrei_spelling(romaji: &str) -> bool {
+    let core = strip_sokuon(romaji);
+    matches!(core, "si" | "zi" | "ti" | "tu" | "hu") || ["sy", "zy", "ty"].iter().any(|prefix| core.starts_with(prefix))
+}
+
+/// Removes the spellings rejected by `is_excluded`.
+/// Kana that would lose every spelling keep their original ones, so every kana stays typable.
+fn filter_spellings(is_excluded: fn(&str) -> bool) -> Vec<(String, Vec<String>)> {
+    get_layout()
+        .into_iter()
+        .map(|(kana, values)| {
+            let kept: Vec<String> = values.iter().filter(|value| !is_excluded(value)).cloned().collect();
+            (kana, if kept.is_empty() { values } else { kept })
+        })
+        .collect()
+}
+
+/// Builds the standard table restricted to Kunrei-shiki spellings (`si`, `tu`, `sya`, ...).
+pub fn get_kunrei_layout() -> Vec<(String, Vec<String>)> {
+    filter_spellings(is_hepburn_spelling)
+}
+
+/// Builds the standard table restricted to Hepburn spellings (`shi`, `tsu`, `sha`, ...).
+pub fn get_hepburn_layout() -> Vec<(String, Vec<String>)> {
+    filter_spellings(is_kunrei_spelling)
+}
+
+/// Lists the standard spellings that a restricted layout no longer accepts.
+pub fn get_rejected_spellings(mapping: &[(String, Vec<String>)]) -> Vec<(String, Vec<String>)> {
+    get_layout()
+        .into_iter()
+        .filter_map(|(kana, values)| {
+            let kept = mapping.iter().find(|(key, _)| *key == kana).map(|(_, kept)| kept);
+            let rejected: Vec<String> = values.into_iter().filter(|value| !kept.is_some_and(|kept| kept.contains(value))).collect();
+            (!rejected.is_empty()).then_some((kana, rejected))
+        })
+        .collect()
+}
+
+/// Lets a single `n` type ん when the next kana does not start with a vowel, `n` or `y`
+/// (`nda` → んだ), by adding an entry for every such ん + kana pair.
+pub fn add_single_n_entries(mapping: &mut Vec<(String, Vec<String>)>) {
+    let mut additions = Vec::new();
+    for (kana, values) in mapping.iter() {
+        if !kana.chars().next().is_some_and(|c| ('ぁ'..='ゖ').contains(&c)) || kana.starts_with('ん') {
+            continue;
+        }
+        for value in values {
+            if value.starts_with(|c: char| c.is_ascii_lowercase() && !"aiueony".contains(c)) {
+                additions.push((String::from("ん") + kana, String::from("n") + value));
+            }
+        }
+    }
+    for (kana, romaji) in additions {
+        add_entry(mapping, &kana, &romaji);
+    }
+}
+
+/// Adds the entries of a user mapping text to a layout.
+/// Each line is a kana followed by one or more romaji spellings, separated by whitespace.
+/// Empty lines and lines starting with `#` are ignored.
+pub fn apply_mapping_text(mapping: &mut Vec<(String, Vec<String>)>, text: &str) {
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        if let Some(kana) = fields.next() {
+            for romaji in fields {
+                add_entry(mapping, kana, romaji);
+            }
+        }
+    }
+}
+
+/// JIS kana layout: (character printed on the key in alphanumeric mode, kana, kana with Shift).
+/// Keys without a small kana or symbol on Shift produce the same kana.
+const JIS_KANA_KEYS: [(char, char, char); 48] = [
+    ('1', 'ぬ', 'ぬ'), ('2', 'ふ', 'ふ'), ('3', 'あ', 'ぁ'), ('4', 'う', 'ぅ'), ('5', 'え', 'ぇ'), ('6', 'お', 'ぉ'),
+    ('7', 'や', 'ゃ'), ('8', 'ゆ', 'ゅ'), ('9', 'よ', 'ょ'), ('0', 'わ', 'を'), ('-', 'ほ', 'ほ'), ('^', 'へ', 'へ'), ('¥', 'ー', 'ー'),
+    ('q', 'た', 'た'), ('w', 'て', 'て'), ('e', 'い', 'ぃ'), ('r', 'す', 'す'), ('t', 'か', 'か'), ('y', 'ん', 'ん'),
+    ('u', 'な', 'な'), ('i', 'に', 'に'), ('o', 'ら', 'ら'), ('p', 'せ', 'せ'), ('@', '゛', '゛'), ('[', '゜', '「'),
+    ('a', 'ち', 'ち'), ('s', 'と', 'と'), ('d', 'し', 'し'), ('f', 'は', 'は'), ('g', 'き', 'き'), ('h', 'く', 'く'),
+    ('j', 'ま', 'ま'), ('k', 'の', 'の'), ('l', 'り', 'り'), (';', 'れ', 'れ'), (':', 'け', 'け'), (']', 'む', '」'),
+    ('z', 'つ', 'っ'), ('x', 'さ', 'さ'), ('c', 'そ', 'そ'), ('v', 'ひ', 'ひ'), ('b', 'こ', 'こ'), ('n', 'み', 'み'),
+    ('m', 'も', 'も'), (',', 'ね', '、'), ('.', 'る', '。'), ('/', 'め', '・'), ('\\', 'ろ', 'ろ'),
+];
+
+/// Characters produced with Shift on a JIS keyboard in alphanumeric mode, paired with the unshifted key.
+const JIS_SHIFTED_ASCII: [(char, char); 21] = [
+    ('!', '1'), ('"', '2'), ('#', '3'), ('$', '4'), ('%', '5'), ('&', '6'), ('\'', '7'), ('(', '8'), (')', '9'),
+    ('=', '-'), ('~', '^'), ('|', '¥'), ('`', '@'), ('{', '['), ('+', ';'), ('*', ':'), ('}', ']'),
+    ('<', ','), ('>', '.'), ('?', '/'), ('_', '\\'),
+];
+
+/// Returns the kana typed by a key of the JIS kana layout.
+/// `key` is the character printed on the key in alphanumeric mode (lowercase for letters).
+pub fn jis_kana_for_key(key: char, shift: bool) -> Option<char> {
+    JIS_KANA_KEYS
+        .iter()
+        .find(|(k, _, _)| *k == key)
+        .map(|(_, kana, shifted)| if shift { *shifted } else { *kana })
+}
+
+/// Returns the kana for a character typed on a JIS keyboard in alphanumeric mode.
+/// Used by backends that only receive characters, not physical keys.
+pub fn jis_kana_for_ascii(c: char) -> Option<char> {
+    if let Some((_, key)) = JIS_SHIFTED_ASCII.iter().find(|(shifted, _)| *shifted == c) {
+        return jis_kana_for_key(*key, true);
+    }
+    if c.is_ascii_uppercase() {
+        return jis_kana_for_key(c.to_ascii_lowercase(), true);
+    }
+    jis_kana_for_key(c, false)
+}
+
+/// Kana typed with a dakuten (゛) or handakuten (゜) keystroke after the base kana.
+const JIS_VOICED_KANA: [(&str, &str); 26] = [
+    ("が", "か゛"), ("ぎ", "き゛"), ("ぐ", "く゛"), ("げ", "け゛"), ("ご", "こ゛"),
+    ("ざ", "さ゛"), ("じ", "し゛"), ("ず", "す゛"), ("ぜ", "せ゛"), ("ぞ", "そ゛"),
+    ("だ", "た゛"), ("ぢ", "ち゛"), ("づ", "つ゛"), ("で", "て゛"), ("ど", "と゛"),
+    ("ば", "は゛"), ("び", "ひ゛"), ("ぶ", "ふ゛"), ("べ", "へ゛"), ("ぼ", "ほ゛"), ("ゔ", "う゛"),
+    ("ぱ", "は゜"), ("ぴ", "ひ゜"), ("ぷ", "ふ゜"), ("ぺ", "へ゜"), ("ぽ", "ほ゜"),
+];
+
+/// Builds the mapping for the JIS kana layout.
+/// Each kana maps to the kana keystrokes that produce it, in the same shape as `get_layout`.
+pub fn get_jis_kana_layout() -> Vec<(String, Vec<String>)> {
+    let mut mapping: Vec<(String, Vec<String>)> = Vec::new();
+    for (_, kana, shifted) in JIS_KANA_KEYS.iter() {
+        for c in [*kana, *shifted] {
+            let key = String::from(c);
+            if !mapping.iter().any(|(k, _)| *k == key) {
+                mapping.push((key.clone(), vec![key]));
+            }
+        }
+    }
+    for (kana, keystrokes) in JIS_VOICED_KANA.iter() {
+        mapping.push((String::from(*kana), vec![String::from(*keystrokes)]));
+    }
+    mapping.push((String::from(" "), vec![String::from(" ")]));
+    mapping.push((String::from("　"), vec![String::from(" ")]));
+    mapping
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jis_kana_for_ascii() {
+        // JISキーボードの英数モードで入力された文字を、同じキーのかなに変換できることを確認
+        assert_eq!(jis_kana_for_ascii('t'), Some('か'));
+        assert_eq!(jis_kana_for_ascii('@'), Some('゛'));
+        assert_eq!(jis_kana_for_ascii('#'), Some('ぁ'));
+        assert_eq!(jis_kana_for_ascii('Z'), Some('っ'));
+        assert_eq!(jis_kana_for_ascii('<'), Some('、'));
+        assert_eq!(jis_kana_for_ascii('Q'), Some('た'));
+        assert_eq!(jis_kana_for_ascii(' '), None);
+    }
+
+    #[test]
+    fn test_azik_layout() {
+        // 拡張入力が行と母音の表どおりのかなに割り当てられ、促音付きのかなに紛れ込まないことを確認
+        let mapping = get_azik_layout();
+        let romaji_for = |kana: &str| {
+            mapping.iter().find(|(key, _)| key == kana).map(|(_, values)| values.clone()).unwrap_or_default()
+        };
+        assert!(romaji_for("びぃん").contains(&String::from("byk")));
+        assert!(!romaji_for("っびぃん").contains(&String::from("byk")));
+        assert!(romaji_for("かん").contains(&String::from("kz")));
+        assert!(romaji_for("こう").contains(&String::from("kp")));
+        assert!(romaji_for("しゃい").contains(&String::from("syq")));
+        assert!(romaji_for("ん").contains(&String::from("q")));
+        assert!(romaji_for("っ").contains(&String::from(";")));
+    }
+
+    #[test]
+    fn test_act_layout() {
+        // ACTの拡張入力がDvorak配列の位置どおりのかなに割り当てられることを確認
+        let mapping = get_act_layout();
+        let romaji_for = |kana: &str| {
+            mapping.iter().find(|(key, _)| key == kana).map(|(_, values)| values.clone()).unwrap_or_default()
+        };
+        assert!(romaji_for("き").contains(&String::from("ci")));
+        assert!(romaji_for("かん").contains(&String::from("c;")));
+        assert!(romaji_for("こう").contains(&String::from("c,")));
+        assert!(romaji_for("びぃん").contains(&String::from("byx")));
+        assert!(!romaji_for("っびぃん").contains(&String::from("byx")));
+        assert!(romaji_for("しゅん").contains(&String::from("syk")));
+        assert!(romaji_for("てい").contains(&String::from("t.")));
+        assert!(romaji_for("さい").contains(&String::from("s'")));
+        // AZIKの拡張は含まない
+        assert!(!romaji_for("かん").contains(&String::from("kz")));
+    }
+}
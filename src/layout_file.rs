@@ -0,0 +1,211 @@
+// src/layout_file.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(not(feature = "uefi"))]
+use std::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use core::fmt;
+
+use crate::layout_data;
+use crate::model::{Layout, LayoutPreset};
+
+/// キー配列ファイルの拡張子
+pub const LAYOUT_FILE_EXTENSION: &str = "ntl";
+
+/// 読み込んだキー配列ファイル
+#[derive(Debug, Clone)]
+pub struct LayoutFile {
+    /// 設定画面に表示する名前（`@name` の指定。なければ呼び出し側が与えた名前）
+    pub name: String,
+    /// 元にした組み込みの配列。`@base none` の場合は `None`
+    pub base: Option<LayoutPreset>,
+    pub layout: Layout,
+    /// 読み込み時に見つかった問題。問題のある行は読み飛ばしている
+    pub issues: Vec<LayoutIssue>,
+}
+
+/// キー配列ファイルの問題点
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutIssue {
+    pub line: usize, // 1から始まる行番号
+    pub message: String,
+}
+
+impl fmt::Display for LayoutIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// `@base` に指定できる名前から組み込みの配列を得る
+fn parse_base(name: &str) -> Option<Option<LayoutPreset>> {
+    match name {
+        "standard" => Some(Some(LayoutPreset::Standard)),
+        "azik" => Some(Some(LayoutPreset::Azik)),
+        "act" => Some(Some(LayoutPreset::Act)),
+        "kunrei" => Some(Some(LayoutPreset::Kunrei)),
+        "hepburn" => Some(Some(LayoutPreset::Hepburn)),
+        "jis" => Some(Some(LayoutPreset::JisKana)),
+        "none" => Some(None),
+        _ => None,
+    }
+}
+
+/// キー配列ファイルを読み込む
+///
+/// 書式は1行に1つの指定で、`#` で始まる行はコメントとして無視する。
+/// - `@name <名前>`: 設定画面に表示する名前
+/// - `@base standard|azik|act|kunrei|hepburn|jis|none`: 元にする配列（既定は standard）。かなの行より前に書く
+/// - `@single-n`: 子音の前の `n` 1文字で「ん」を入力できるようにする
+/// - `<かな> <綴り> <綴り>...`: かなに綴りを追加する
+///
+/// 読み込めない行は読み飛ばして `issues` に記録し、あわせて入力できなくなる綴りがないかを検査する。
+pub fn parse_layout_file(text: &str, default_name: &str) -> LayoutFile {
+    let mut name = default_name.to_string();
+    let mut base = Some(LayoutPreset::Standard);
+    let mut single_n = false;
+    let mut entries: Vec<(usize, String, String)> = Vec::new();
+    let mut issues = Vec::new();
+
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let Some(first) = fields.next() else { continue };
+        if let Some(directive) = first.strip_prefix('@') {
+            let argument = line[first.len()..].trim();
+            match directive {
+                "name" if !argument.is_empty() => name = argument.to_string(),
+                "base" => match parse_base(argument) {
+                    Some(_) if !entries.is_empty() => issues.push(LayoutIssue {
+                        line: line_number,
+                        message: "@base must come before the kana entries".to_string(),
+                    }),
+                    Some(preset) => base = preset,
+                    None => issues.push(LayoutIssue {
+                        line: line_number,
+                        message: format!("unknown base layout '{}'", argument),
+                    }),
+                },
+                "single-n" => single_n = true,
+                _ => issues.push(LayoutIssue { line: line_number, message: format!("unknown directive '{}'", first) }),
+            }
+            continue;
+        }
+        let spellings: Vec<&str> = fields.collect();
+        if spellings.is_empty() {
+            issues.push(LayoutIssue { line: line_number, message: format!("'{}' has no spellings", first) });
+        }
+        for spelling in spellings {
+            if entries.iter().any(|(_, kana, romaji)| kana == first && romaji == spelling) {
+                issues.push(LayoutIssue {
+                    line: line_number,
+                    message: format!("'{}' is listed twice for '{}'", spelling, first),
+                });
+                continue;
+            }
+            entries.push((line_number, first.to_string(), spelling.to_string()));
+        }
+    }
+
+    let mut layout = match base {
+        Some(preset) => Layout::from_preset(preset),
+        None => Layout { mapping: Vec::new(), rejected: Vec::new() },
+    };
+    for (_, kana, romaji) in &entries {
+        layout_data::add_entry(&mut layout.mapping, kana, romaji);
+    }
+    if single_n {
+        layout_data::add_single_n_entries(&mut layout.mapping);
+    }
+    // ファイルで追加した綴りは、制限された配列でも受け付ける綴りとして扱う
+    for (kana, values) in layout.rejected.iter_mut() {
+        values.retain(|value| !entries.iter().any(|(_, k, r)| k == kana && r == value));
+    }
+    layout.rejected.retain(|(_, values)| !values.is_empty());
+
+    for (line, kana, romaji) in &entries {
+        if let Some(message) = find_ambiguity(&layout.mapping, kana, romaji) {
+            issues.push(LayoutIssue { line: *line, message });
+        }
+    }
+
+    LayoutFile { name, base, layout, issues }
+}
+
+/// 綴りが、配列の他の項目のせいで入力できなくなっていないかを調べる
+///
+/// 入力と完全に一致した項目はその場で確定するため、同じ綴りを持つ他のかなや、
+/// この綴りから始まるより長い綴りは、続くかなが一致する場面で入力できなくなる。
+fn find_ambiguity(mapping: &[(String, Vec<String>)], kana: &str, romaji: &str) -> Option<String> {
+    for (other_kana, values) in mapping {
+        let overlaps = other_kana.starts_with(kana) || kana.starts_with(other_kana.as_str());
+        if !overlaps {
+            continue;
+        }
+        for value in values {
+            if other_kana != kana && value == romaji {
+                return Some(format!("'{}' is used for both '{}' and '{}'", romaji, kana, other_kana));
+            }
+            if other_kana.starts_with(kana) && value.len() > romaji.len() && value.starts_with(romaji) {
+                return Some(format!(
+                    "'{}' for '{}' completes before '{}' for '{}' can be typed",
+                    romaji, kana, value, other_kana
+                ));
+            }
+            if kana.starts_with(other_kana.as_str()) && romaji.len() > value.len() && romaji.starts_with(value.as_str()) {
+                return Some(format!(
+                    "'{}' for '{}' completes before '{}' for '{}' can be typed",
+                    value, other_kana, romaji, kana
+                ));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spellings<'a>(file: &'a LayoutFile, kana: &str) -> &'a [String] {
+        file.layout.mapping.iter().find(|(key, _)| key == kana).map_or(&[], |(_, values)| values.as_slice())
+    }
+
+    #[test]
+    fn test_parse_layout_file() {
+        // 指定と綴りが読み込まれることを確認
+        let file = parse_layout_file("# 例\n@name My Layout\n@base hepburn\n@single-n\nし si\nゔ vu\n", "fallback");
+        assert_eq!(file.name, "My Layout");
+        assert_eq!(file.base, Some(LayoutPreset::Hepburn));
+        assert!(file.issues.is_empty(), "{:?}", file.issues);
+        assert!(spellings(&file, "し").contains(&"si".to_string()));
+        assert!(spellings(&file, "んだ").contains(&"nda".to_string()));
+        // 追加した綴りは制限の対象から外れる
+        assert!(!file.layout.rejected.iter().any(|(kana, values)| kana == "し" && values.contains(&"si".to_string())));
+    }
+
+    #[test]
+    fn test_layout_file_issues() {
+        // 読み込めない行と、入力できなくなる綴りが報告されることを確認
+        let file = parse_layout_file("@base none\nか ka\nかん ka\nん n nn\nあ\n@color red\nか ka\n@base standard\n", "x");
+        let lines: Vec<usize> = file.issues.iter().map(|issue| issue.line).collect();
+        assert_eq!(lines, vec![5, 6, 7, 8, 2, 3, 4, 4]);
+        assert_eq!(file.name, "x");
+        assert_eq!(file.base, None);
+    }
+}
@@ -20,10 +20,25 @@ pub mod app;
 pub mod renderer;
 pub mod ui;
 pub mod parser;
+pub mod serializer;
+pub mod aozora;
+pub mod html;
 pub mod model;
 pub mod layout_data;
+pub mod layout_file;
 pub mod typing;
 pub mod timestamp;
+pub mod storage;
+pub mod history;
+pub mod random;
+pub mod review;
+pub mod drill;
+pub mod endless;
+pub mod karuta;
+
+// 問題ファイルの変換などを行うコマンドラインモジュール（ファイルシステムを使うためuefiでは無効）
+#[cfg(not(feature = "uefi"))]
+pub mod cli;
 
 // "gui" featureが有効な時だけコンパイルされるGUIバックエンドモジュール
 #[cfg(feature = "gui")]
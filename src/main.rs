@@ -5,6 +5,12 @@
 /// main関数 - featureフラグに応じて各バックエンドを起動
 #[cfg(not(feature = "uefi"))]
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // サブコマンドが指定された場合は、バックエンドを起動せずにそのコマンドだけを実行する
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = rust_multibackend_app::cli::run(&args) {
+        return result;
+    }
+
     // "gui" featureが有効な場合にコンパイルされるブロック
     #[cfg(feature = "gui")]
     {
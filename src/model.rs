@@ -5,7 +5,11 @@
 extern crate alloc;
 
 #[cfg(feature = "uefi")]
-use alloc::{string::String, vec::Vec};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 #[cfg(not(feature = "uefi"))]
 use std::string::String;
 #[cfg(not(feature = "uefi"))]
@@ -23,11 +27,43 @@ use crate::layout_data;
 pub struct Content {
     pub title: Line,
     pub lines: Vec<Line>,
+    pub options: ProblemOptions,
+}
+
+/// 問題ファイルの `#mode` などの指定で与えられる設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ProblemOptions {
+    pub mode: InputMode,
+    pub latin_rules: LatinRules,
+}
+
+/// 問題文の入力方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputMode {
+    /// 本文の文字種から自動で判定する
+    #[default]
+    Auto,
+    /// 読みをローマ字またはかなで入力する
+    Japanese,
+    /// 本文を1文字ずつそのまま入力する
+    Latin,
+}
+
+/// 英字モードでの照合規則。既定ではすべて厳密に照合する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LatinRules {
+    /// 大文字と小文字を区別しない
+    pub ignore_case: bool,
+    /// 曲がった引用符やダッシュを、対応するASCII記号での入力でも受け付ける
+    pub loose_punctuation: bool,
+    /// 連続する空白を1回の入力でまとめて進める
+    pub collapse_spaces: bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Line {
     pub words: Vec<Word>,
+    pub split: Option<usize>, // 上の句と下の句の区切り（下の句の最初の単語の番号）。問題ファイルで `|` を書いた行だけSome
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -75,11 +111,76 @@ pub struct TypingModel {
     pub typing_correctness: TypingCorrectnessContent,
     pub layout: Layout,
     pub scroll: Scroll,
+    pub mode: InputMode, // セッション開始時に決定した入力方式（Autoにはならない）
+    pub spelling: SpellingStats,
+    pub preferences: SpellingPreferences, // 入力を完了した綴りの記録。セッション開始時にAppから受け取る
+    pub start_time: Option<f64>, // 入力の受け付けを始めた時刻（カウントダウンの終わり）。Noneの場合は反応時間を求めない
+    pub time_limit: Option<TimeLimit>, // 時間制限のある練習。Noneなら問題を最後まで入力して終わる
+    pub error_policy: ErrorPolicy, // ミスをした際の扱い
+}
+
+/// ミスをした際の扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// ミスした文字を打ち直せば、そのまま先に進める
+    #[default]
+    Continue,
+    /// ミスした単語を最初から打ち直す
+    Strict,
+    /// ミスの回数が上限に達した時点でセッションを終える
+    SuddenDeath { max_misses: u32 },
+    /// ミス1回ごとに、入力にかかった時間へ罰則の時間を加える
+    Penalty { seconds: u32 },
+}
+
+impl ErrorPolicy {
+    /// ミス1回ごとに加える時間（ミリ秒）
+    pub fn penalty_per_miss(self) -> f64 {
+        match self {
+            ErrorPolicy::Penalty { seconds } => seconds as f64 * 1000.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// 問題選択画面で指定する、問題のどの行をどの順で入力するかの設定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionOptions {
+    pub line_count: Option<usize>, // 入力する行数。Noneならすべての行
+    pub shuffle: bool, // 行をランダムな順序に並べ替える
+    pub seed: Option<u64>, // 並べ替えに使うシード。Noneならセッションごとに新しいシードを使う
+    pub start_line: usize, // この行（1から始まる）から入力する
+    pub karuta: bool, // 上の句を見て下の句を入力する。区切りのない行は除く
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self { line_count: None, shuffle: false, seed: None, start_line: 1, karuta: false }
+    }
+}
+
+/// 時間制限のある練習の設定
+///
+/// 問題の行を繰り返して並べ、制限時間が来た時点でセッションを終える。
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeLimit {
+    pub duration: f64, // 制限時間（ミリ秒）
+    pub lap_lines: usize, // 元の問題の行数。何周目のどの行まで進んだかを求めるのに使う
 }
 
 #[derive(Debug, Clone)]
 pub struct ResultModel {
     pub typing_model: TypingModel,
+    pub efficiency: KeystrokeEfficiency, // 完了時に一度だけ計算する
+}
+
+/// 実際の打鍵数と、問題を入力するのに必要な最少の打鍵数との比較
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct KeystrokeEfficiency {
+    pub minimal: usize, // 配列で最も短い綴りを使った場合の打鍵数
+    pub preferred: usize, // 利用者がよく使う綴りで入力した場合の打鍵数
+    pub actual: usize, // 正しく入力した打鍵数
+    pub ratio: f64, // minimal / actual
 }
 
 #[derive(Debug, Clone)]
@@ -103,6 +204,85 @@ pub struct TypingInput {
     pub key: char,
     pub timestamp: f64,
     pub is_correct: bool,
+    pub word: i32, // 打鍵した時に入力中だった、行の中の単語の位置
+}
+
+/// ローマ字の綴りの使い分けの記録
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpellingStats {
+    pub hepburn: i32, // ヘボン式の綴り（shi, tsu など）を打った回数。受け付けられなかった入力も含む
+    pub kunrei: i32, // 訓令式の綴り（si, tu など）を打った回数。受け付けられなかった入力も含む
+    pub rejected: i32, // ローマ字としては正しいが、配列で許可されていない綴りでミスになった回数
+}
+
+/// かなごとに、利用者が実際に入力を完了した綴りとその回数
+///
+/// セッションをまたいで保存し、ローマ字のヒントや最適な打鍵数の計算で綴りを選ぶのに使う。
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpellingPreferences {
+    pub counts: Vec<(String, Vec<(String, u32)>)>,
+}
+
+impl SpellingPreferences {
+    /// かなと綴りの組の回数への参照を得る。記録がなければ0回として追加する
+    fn count_mut(&mut self, kana: &str, romaji: &str) -> &mut u32 {
+        let index = match self.counts.iter().position(|(key, _)| key == kana) {
+            Some(index) => index,
+            None => {
+                self.counts.push((kana.to_string(), Vec::new()));
+                self.counts.len() - 1
+            }
+        };
+        let spellings = &mut self.counts[index].1;
+        let index = match spellings.iter().position(|(spelling, _)| spelling == romaji) {
+            Some(index) => index,
+            None => {
+                spellings.push((romaji.to_string(), 0));
+                spellings.len() - 1
+            }
+        };
+        &mut spellings[index].1
+    }
+
+    /// かなを綴りで入力したことを記録する
+    pub fn record(&mut self, kana: &str, romaji: &str) {
+        *self.count_mut(kana, romaji) += 1;
+    }
+
+    /// 候補の中から、かなに対して最も多く使われた綴りを返す。記録がなければ `None`
+    pub fn preferred<'a>(&self, kana: &str, candidates: &'a [String]) -> Option<&'a String> {
+        let (_, spellings) = self.counts.iter().find(|(key, _)| key == kana)?;
+        candidates
+            .iter()
+            .filter_map(|candidate| {
+                spellings.iter().find(|(spelling, _)| spelling == candidate).map(|(_, count)| (candidate, *count))
+            })
+            .max_by_key(|(_, count)| *count)
+            .map(|(candidate, _)| candidate)
+    }
+
+    /// 保存用のテキストに変換する。1行に「かな 綴り 回数」をタブ区切りで並べる
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (kana, spellings) in &self.counts {
+            for (romaji, count) in spellings {
+                out.push_str(&format!("{}\t{}\t{}\n", kana, romaji, count));
+            }
+        }
+        out
+    }
+
+    /// `to_text` で保存したテキストから読み込む。読めない行は無視する
+    pub fn from_text(text: &str) -> Self {
+        let mut preferences = SpellingPreferences::default();
+        for line in text.lines() {
+            let mut fields = line.split('\t');
+            if let (Some(kana), Some(romaji), Some(Ok(count))) = (fields.next(), fields.next(), fields.next().map(str::parse::<u32>)) {
+                *preferences.count_mut(kana, romaji) = count;
+            }
+        }
+        preferences
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -139,11 +319,20 @@ pub struct TypingMetrics {
     pub total_time: f64,
     pub accuracy: f64,
     pub speed: f64, // Chars per second
+    pub wpm: f64, // 全打鍵を5打鍵で1語として数えた毎分の語数
+    pub net_wpm: f64, // wpmからミス1回につき1語を差し引いたもの
+    pub corrected_wpm: f64, // 正しい打鍵だけを数えたwpm
+    pub active_time: f64, // 打鍵の間隔が一時停止とみなす長さを超えた部分を除いた時間（ミリ秒）
+    pub sustained_speed: f64, // active_timeあたりの正しい打鍵数（毎秒）
+    pub burst_speed: f64, // 直近10秒間の速度のうち最も速かったもの（毎秒）
+    pub reaction_time: Option<f64>, // 入力の受け付け開始から最初の打鍵までの時間（ミリ秒）。total_timeには含まない
+    pub penalty_time: f64, // ミスの罰則として total_time と active_time に加えた時間（ミリ秒）
 }
 
 #[derive(Debug, Clone)]
 pub struct Layout {
     pub mapping: Vec<(String, Vec<String>)>,
+    pub rejected: Vec<(String, Vec<String>)>, // ローマ字としては正しいが、この配列では受け付けない綴り
 }
 
 #[derive(Debug, Clone)]
@@ -152,10 +341,50 @@ pub struct Scroll {
     pub max: f64,
 }
 
+/// 組み込みのキー配列
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutPreset {
+    /// 一般的なローマ字入力（訓令式・ヘボン式の両方を受け付ける）
+    Standard,
+    /// 撥音拡張・二重母音拡張を加えたAZIK
+    Azik,
+    /// Dvorak配列向けの撥音拡張・二重母音拡張と、か行の `c` を加えたACT
+    Act,
+    /// 訓令式の綴りだけを受け付けるローマ字入力
+    Kunrei,
+    /// ヘボン式の綴りだけを受け付けるローマ字入力
+    Hepburn,
+    /// JISかな配列によるかな入力
+    JisKana,
+}
+
+impl Layout {
+    /// 組み込みのキー配列から作る
+    pub fn from_preset(preset: LayoutPreset) -> Self {
+        let mapping = match preset {
+            LayoutPreset::Standard => layout_data::get_layout(),
+            LayoutPreset::Azik => layout_data::get_azik_layout(),
+            LayoutPreset::Act => layout_data::get_act_layout(),
+            LayoutPreset::Kunrei => layout_data::get_kunrei_layout(),
+            LayoutPreset::Hepburn => layout_data::get_hepburn_layout(),
+            LayoutPreset::JisKana => layout_data::get_jis_kana_layout(),
+        };
+        let rejected = match preset {
+            LayoutPreset::Kunrei | LayoutPreset::Hepburn => layout_data::get_rejected_spellings(&mapping),
+            _ => Vec::new(),
+        };
+        Layout { mapping, rejected }
+    }
+
+    /// ユーザーが用意したマッピングファイルの内容を追加する
+    pub fn with_mapping_text(mut self, text: &str) -> Self {
+        layout_data::apply_mapping_text(&mut self.mapping, text);
+        self
+    }
+}
+
 impl Default for Layout {
     fn default() -> Self {
-        Layout {
-            mapping: layout_data::get_layout(),
-        }
+        Layout::from_preset(LayoutPreset::Standard)
     }
 }
\ No newline at end of file
@@ -4,13 +4,13 @@
 extern crate alloc;
 
 #[cfg(feature = "uefi")]
-use alloc::{string::{String, ToString}, vec::Vec};
+use alloc::{string::{String, ToString}, vec, vec::Vec};
 #[cfg(not(feature = "uefi"))]
 use std::string::{String, ToString};
 #[cfg(not(feature = "uefi"))]
 use std::vec::Vec;
 
-use crate::model::{Content, Line, Segment, Word};
+use crate::model::{Content, InputMode, Line, ProblemOptions, Segment, Word};
 
 // --- New Parser Implementation ---
 
@@ -20,6 +20,7 @@ enum Token {
     Hyphen,
     Separator, // '/'
     Space,
+    Split, // '|'。上の句と下の句の区切り
 }
 
 // Stage 1: Tokenize the input line
@@ -31,7 +32,7 @@ fn tokenize_line(line: &str) -> Vec<Token> {
 
     let flush_plain = |plain: &mut String, tokens: &mut Vec<Token>| {
         if !plain.is_empty() {
-            tokens.push(Token::Segment(Segment::Plain { text: std::mem::take(plain) }));
+            tokens.push(Token::Segment(Segment::Plain { text: core::mem::take(plain) }));
         }
     };
 
@@ -62,6 +63,10 @@ fn tokenize_line(line: &str) -> Vec<Token> {
                 flush_plain(&mut plain_text, &mut tokens);
                 tokens.push(Token::Space);
             }
+            '|' => { // Half split (also a word separator)
+                flush_plain(&mut plain_text, &mut tokens);
+                tokens.push(Token::Split);
+            }
             _ => { // Plain text character
                 plain_text.push(chars[pos]);
             }
@@ -120,7 +125,7 @@ fn group_tokens_into_words(tokens: Vec<Token>) -> Vec<Word> {
 
     let finalize_current_word = |segments: &mut Vec<Segment>, words: &mut Vec<Word>| {
         if !segments.is_empty() {
-            words.push(Word { segments: std::mem::take(segments) });
+            words.push(Word { segments: core::mem::take(segments) });
         }
     };
 
@@ -156,7 +161,7 @@ fn group_tokens_into_words(tokens: Vec<Token>) -> Vec<Word> {
                     last_token_was_connector = false;
                 }
             }
-            Token::Separator => {
+            Token::Separator | Token::Split => {
                 finalize_current_word(&mut current_segments, &mut words);
                 last_token_was_connector = false;
             }
@@ -173,6 +178,21 @@ fn group_tokens_into_words(tokens: Vec<Token>) -> Vec<Word> {
     words
 }
 
+/// 本文の1行を解析する
+///
+/// 最初の `|` の位置を、上の句と下の句の区切りとして記録する。2つ目以降の `|` は単語の区切りとしてだけ扱う。
+fn parse_line(line: &str) -> Line {
+    let mut tokens = tokenize_line(line);
+    let Some(index) = tokens.iter().position(|token| *token == Token::Split) else {
+        return Line { words: group_tokens_into_words(tokens), split: None };
+    };
+    let rest = tokens.split_off(index + 1);
+    tokens.pop();
+    let mut words = group_tokens_into_words(tokens);
+    let split = Some(words.len());
+    words.extend(group_tokens_into_words(rest));
+    Line { words, split }
+}
 
 // Main parser function called by the application
 pub fn parse_problem(input: &str) -> Content {
@@ -185,23 +205,55 @@ pub fn parse_problem(input: &str) -> Content {
         // タイトル行も本文と同様にトークン化し、単語にグループ化する
         let tokens = tokenize_line(content);
         let words = group_tokens_into_words(tokens);
-        Line { words }
+        Line { words, split: None }
     } else {
-        Line { words: Vec::new() }
+        Line { words: Vec::new(), split: None }
     };
 
     // Parse the remaining content lines
+    let mut options = ProblemOptions::default();
+    let mut in_header = true;
     let mut lines = Vec::new();
     for line_str in lines_iter {
         if line_str.trim().is_empty() {
             continue;
         }
-        let tokens = tokenize_line(line_str);
-        let words = group_tokens_into_words(tokens);
-        lines.push(Line { words });
+        // タイトル直後に続く `#` 指定は設定として読み取る
+        if in_header && apply_directive(&mut options, line_str) {
+            continue;
+        }
+        in_header = false;
+        lines.push(parse_line(line_str));
     }
 
-    Content { title, lines }
+    Content { title, lines, options }
+}
+
+/// `#mode latin` のような指定行を設定に反映する
+///
+/// 指定として解釈できない行の場合は `false` を返し、本文として扱わせる。
+fn apply_directive(options: &mut ProblemOptions, line: &str) -> bool {
+    let Some(directive) = line.strip_prefix('#') else {
+        return false;
+    };
+    let mut parts = directive.split_whitespace();
+    let (Some(name), Some(value), None) = (parts.next(), parts.next(), parts.next()) else {
+        return false;
+    };
+    let rules = &mut options.latin_rules;
+    match (name, value) {
+        ("mode", "auto") => options.mode = InputMode::Auto,
+        ("mode", "japanese") => options.mode = InputMode::Japanese,
+        ("mode", "latin") => options.mode = InputMode::Latin,
+        ("case", "strict") => rules.ignore_case = false,
+        ("case", "ignore") => rules.ignore_case = true,
+        ("punctuation", "strict") => rules.loose_punctuation = false,
+        ("punctuation", "loose") => rules.loose_punctuation = true,
+        ("spacing", "strict") => rules.collapse_spaces = false,
+        ("spacing", "collapse") => rules.collapse_spaces = true,
+        _ => return false,
+    }
+    true
 }
 
 
@@ -455,4 +507,34 @@ mod tests {
         ];
         assert_eq!(parse_line_to_words(line), expected);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_directives_after_title() {
+        // タイトル直後の `#` 指定が設定として読み取られ、本文には含まれないことを確認
+        let content = parse_problem("#title License\n#mode latin\n#case ignore\n#spacing collapse\nMIT License\n#mode japanese");
+        assert_eq!(content.options.mode, InputMode::Latin);
+        assert!(content.options.latin_rules.ignore_case);
+        assert!(!content.options.latin_rules.loose_punctuation);
+        assert!(content.options.latin_rules.collapse_spaces);
+        // 本文が始まった後の `#` 行は本文として扱う
+        assert_eq!(content.lines.len(), 2);
+        assert_eq!(content.lines[1].to_string(), "#mode japanese");
+
+        // 解釈できない指定は本文として扱う
+        let content = parse_problem("#title x\n#mode klingon");
+        assert_eq!(content.options, ProblemOptions::default());
+        assert_eq!(content.lines[0].to_string(), "#mode klingon");
+    }
+
+    #[test]
+    fn test_half_split() {
+        // 最初の `|` が上の句と下の句の区切りとして記録され、単語の区切りとしても働くことを確認
+        let content = parse_problem("#title x\n(秋/あき)の|(露/つゆ)に|ぬれ\nかな\n\\|\n");
+        assert_eq!(content.lines[0].split, Some(2));
+        assert_eq!(content.lines[0].words.len(), 5);
+        assert_eq!(content.lines[1].split, None);
+        // エスケープした `|` は文字として扱う
+        assert_eq!(content.lines[2].split, None);
+        assert_eq!(content.lines[2].to_string(), "|");
+    }
+}
@@ -0,0 +1,47 @@
+// src/random.rs
+
+/// 再現可能な擬似乱数の生成器（SplitMix64）
+///
+/// 同じシードからは常に同じ列を返すため、シャッフルした問題などを後から同じ順序で再現できる。
+/// 暗号用途には使わないこと。
+#[derive(Debug, Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// 次の64ビットの値
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// `0..bound` の範囲の値。`bound` が0の場合は0を返す
+    pub fn below(&mut self, bound: usize) -> usize {
+        if bound == 0 {
+            return 0;
+        }
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// 要素の順序を一様にシャッフルする（Fisher-Yates）
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i + 1);
+            items.swap(i, j);
+        }
+    }
+}
+
+/// 現在時刻から、シードを指定しない場合に使うシードを作る
+pub fn seed_from_time() -> u64 {
+    // 表示して入力し直せるよう、6桁に収める
+    (crate::timestamp::now() as u64) % 1_000_000
+}
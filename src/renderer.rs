@@ -7,14 +7,14 @@ extern crate alloc;
 #[cfg(feature = "uefi")]
 use alloc::vec;
 #[cfg(feature = "uefi")]
-use alloc::vec::Vec;
+use alloc::{format, string::String, vec::Vec};
 #[cfg(not(feature = "uefi"))]
 use std::vec::Vec;
 
 #[cfg(feature = "uefi")]
 use core_maths::CoreFloat;
 
-use crate::ui::FontSize;
+use crate::ui::{ChartSeries, FontSize, CHART_AXIS_COLOR};
 use ab_glyph::{point, Font, FontRef, OutlinedGlyph, PxScale, ScaleFont};
 
 /// 背景の描画色
@@ -74,6 +74,15 @@ pub fn calculate_pixel_font_size(font_size: FontSize, width: usize, height: usiz
     }
 }
 
+/// グラフの目盛りに表示する数値の書式。整数ならそのまま、そうでなければ小数点以下1桁で表す
+pub fn format_chart_value(value: f32) -> String {
+    if value.fract() == 0.0 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
 /// GUI/WASMバックエンド用のピクセルベースレンダラ
 pub mod gui_renderer {
     use super::*;
@@ -163,6 +172,97 @@ pub mod gui_renderer {
         }
     }
 
+    /// ピクセルバッファに1ピクセル幅の線分を描画する
+    pub fn draw_line(buffer: &mut [u32], width: usize, from: (f32, f32), to: (f32, f32), color: u32) {
+        let height = buffer.len() / width;
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let steps = dx.abs().max(dy.abs()).ceil().max(1.0) as usize;
+        for i in 0..=steps {
+            let t = i as f32 / steps as f32;
+            let x = (from.0 + dx * t).round();
+            let y = (from.1 + dy * t).round();
+            if x >= 0.0 && y >= 0.0 && (x as usize) < width && (y as usize) < height {
+                buffer[y as usize * width + x as usize] = color;
+            }
+        }
+    }
+
+    /// 指定した矩形に、軸・目盛り・凡例付きの折れ線グラフを描画する
+    ///
+    /// 1つ目の系列の縦軸の目盛りを左に、2つ目の系列の目盛りを右に表示する。
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw_line_chart(
+        buffer: &mut [u32],
+        width: usize,
+        font: &FontRef,
+        rect: (f32, f32, f32, f32),
+        series: &[ChartSeries],
+        x_max: f32,
+        x_label: &str,
+        font_size: f32,
+    ) {
+        let (rect_x, rect_y, rect_w, rect_h) = rect;
+        let y_labels: Vec<String> = series.iter().take(2).map(|s| format_chart_value(s.y_max)).collect();
+        let label_width = |index: usize| {
+            y_labels.get(index).map_or(0.0, |label| measure_text(font, label, font_size).0 as f32 + font_size * 0.3)
+        };
+        let plot_left = rect_x + label_width(0);
+        let plot_right = rect_x + rect_w - label_width(1);
+        // 上に凡例、下に横軸の目盛りの行を取る
+        let plot_top = rect_y + font_size * 1.4;
+        let plot_bottom = rect_y + rect_h - font_size * 1.2;
+        if plot_right <= plot_left || plot_bottom <= plot_top {
+            return;
+        }
+        let plot_w = plot_right - plot_left;
+        let plot_h = plot_bottom - plot_top;
+
+        // 凡例
+        let mut legend_x = plot_left;
+        for s in series {
+            draw_rect(buffer, width, legend_x as usize, (rect_y + font_size * 0.35) as usize, (font_size * 0.6) as usize, (font_size * 0.3) as usize, s.color);
+            legend_x += font_size * 0.9;
+            draw_text(buffer, width, font, &s.label, (legend_x, rect_y), font_size, s.color);
+            legend_x += measure_text(font, &s.label, font_size).0 as f32 + font_size;
+        }
+
+        // 軸と枠
+        draw_line(buffer, width, (plot_left, plot_top), (plot_left, plot_bottom), CHART_AXIS_COLOR);
+        draw_line(buffer, width, (plot_left, plot_bottom), (plot_right, plot_bottom), CHART_AXIS_COLOR);
+        if series.len() > 1 {
+            draw_line(buffer, width, (plot_right, plot_top), (plot_right, plot_bottom), CHART_AXIS_COLOR);
+        }
+
+        // 目盛り
+        let text_h = measure_text(font, "0", font_size).1 as f32;
+        if let Some(label) = y_labels.first() {
+            let label_w = measure_text(font, label, font_size).0 as f32;
+            draw_text(buffer, width, font, label, (plot_left - label_w - font_size * 0.2, plot_top - text_h / 2.0), font_size, series[0].color);
+            draw_text(buffer, width, font, "0", (plot_left - measure_text(font, "0", font_size).0 as f32 - font_size * 0.2, plot_bottom - text_h / 2.0), font_size, CHART_AXIS_COLOR);
+        }
+        if let Some(label) = y_labels.get(1) {
+            draw_text(buffer, width, font, label, (plot_right + font_size * 0.2, plot_top - text_h / 2.0), font_size, series[1].color);
+        }
+        draw_text(buffer, width, font, "0", (plot_left, plot_bottom + font_size * 0.1), font_size, CHART_AXIS_COLOR);
+        let x_max_label = format!("{}s", format_chart_value(x_max.round()));
+        let x_max_w = measure_text(font, &x_max_label, font_size).0 as f32;
+        draw_text(buffer, width, font, &x_max_label, (plot_right - x_max_w, plot_bottom + font_size * 0.1), font_size, CHART_AXIS_COLOR);
+        let x_label_w = measure_text(font, x_label, font_size).0 as f32;
+        draw_text(buffer, width, font, x_label, (plot_left + (plot_w - x_label_w) / 2.0, plot_bottom + font_size * 0.1), font_size, CHART_AXIS_COLOR);
+
+        // 折れ線
+        for s in series {
+            let points: Vec<(f32, f32)> = s
+                .normalized_points(x_max)
+                .into_iter()
+                .map(|(nx, ny)| (plot_left + nx * plot_w, plot_bottom - ny * plot_h))
+                .collect();
+            for pair in points.windows(2) {
+                draw_line(buffer, width, pair[0], pair[1], s.color);
+            }
+        }
+    }
+
     /// テキストの描画サイズ（幅と高さ）を計算する
     pub fn measure_text(font: &FontRef, text: &str, size: f32) -> (u32, u32, f32) {
         let scale = PxScale::from(size);
@@ -423,4 +523,57 @@ pub mod tui_renderer {
 
         (char_buffer, art_width, art_height, ascent_in_cells)
     }
-}
\ No newline at end of file
+
+    /// 折れ線グラフを点字で描画し、(文字バッファ, 各セルを描いた系列の番号) を返す
+    ///
+    /// `series` の各点は `ChartSeries::normalized_points` と同じく0.0〜1.0の比率で、縦軸は下が0。
+    /// 複数の系列が同じセルを通る場合は、後の系列の色を優先する。
+    pub fn render_line_chart_to_braille(
+        series: &[Vec<(f32, f32)>],
+        width: usize,
+        height: usize,
+    ) -> (Vec<char>, Vec<Option<usize>>) {
+        let sub_w = width * 2;
+        let sub_h = height * 4;
+        let mut dots: Vec<Option<usize>> = vec![None; sub_w * sub_h];
+        if sub_w == 0 || sub_h == 0 {
+            return (Vec::new(), Vec::new());
+        }
+        let to_dot = |(nx, ny): (f32, f32)| (nx * (sub_w - 1) as f32, (1.0 - ny) * (sub_h - 1) as f32);
+        for (index, points) in series.iter().enumerate() {
+            for pair in points.windows(2) {
+                let (from, to) = (to_dot(pair[0]), to_dot(pair[1]));
+                let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil().max(1.0) as usize;
+                for i in 0..=steps {
+                    let t = i as f32 / steps as f32;
+                    let x = (from.0 + (to.0 - from.0) * t).round() as usize;
+                    let y = (from.1 + (to.1 - from.1) * t).round() as usize;
+                    if x < sub_w && y < sub_h {
+                        dots[y * sub_w + x] = Some(index);
+                    }
+                }
+            }
+        }
+
+        const BIT_MAP: [[u8; 2]; 4] = [[0, 3], [1, 4], [2, 5], [6, 7]];
+        let mut char_buffer = Vec::with_capacity(width * height);
+        let mut owners = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut braille_byte: u32 = 0;
+                let mut owner = None;
+                for (dy, bits) in BIT_MAP.iter().enumerate() {
+                    for (dx, bit) in bits.iter().enumerate() {
+                        if let Some(index) = dots[(y * 4 + dy) * sub_w + x * 2 + dx] {
+                            braille_byte |= 1 << bit;
+                            owner = owner.max(Some(index));
+                        }
+                    }
+                }
+                char_buffer.push(char::try_from(0x2800 + braille_byte).unwrap_or(' '));
+                owners.push(owner);
+            }
+        }
+        (char_buffer, owners)
+    }
+}
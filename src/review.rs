@@ -0,0 +1,259 @@
+// src/review.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+#[cfg(not(feature = "uefi"))]
+use std::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+// uefi で f64::round() を使うために必要
+#[cfg(feature = "uefi")]
+use core_maths::CoreFloat;
+
+use crate::model::{Content, InputMode, TypingCorrectnessChar, TypingModel};
+use crate::parser;
+use crate::serializer;
+use crate::storage::sanitize_field;
+use crate::typing;
+
+/// 保存する際のキー
+pub const REVIEW_KEY: &str = "review";
+
+/// 1回の復習で出題する単語の最大数
+pub const REVIEW_SESSION_SIZE: usize = 20;
+
+/// 1日の長さ（ミリ秒）
+const DAY_MS: f64 = 86_400_000.0;
+
+/// 新しい項目の易しさ（SM-2の初期値）
+const INITIAL_EASE: f64 = 2.5;
+
+/// 易しさの下限（SM-2の下限値）
+const MIN_EASE: f64 = 1.3;
+
+/// 間違えた単語1つ分の復習の予定
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewItem {
+    pub problem: String, // 出題元の問題ファイルの名前
+    pub word: String, // 問題ファイルの形式で書いた単語（ルビを含む）
+    pub mode: InputMode, // 出題元の入力方式（JapaneseかLatin）
+    pub ease: f64, // SM-2の易しさ。間隔を延ばす倍率
+    pub interval: u32, // 次の復習までの日数
+    pub repetitions: u32, // 続けて正しく入力できた回数
+    pub due: f64, // 次に出題する時刻（ミリ秒）
+}
+
+impl ReviewItem {
+    /// 入力の出来（0〜5）に応じて、SM-2の方式で次の復習の予定を決める
+    fn schedule(&mut self, quality: u32, now: f64) {
+        if quality >= 3 {
+            self.interval = match self.repetitions {
+                0 => 1,
+                1 => 6,
+                _ => (self.interval as f64 * self.ease).round() as u32,
+            };
+            self.repetitions += 1;
+        } else {
+            self.repetitions = 0;
+            self.interval = 1;
+        }
+        let lapse = (5 - quality.min(5)) as f64;
+        self.ease = (self.ease + 0.1 - lapse * (0.08 + lapse * 0.02)).max(MIN_EASE);
+        self.due = now + self.interval as f64 * DAY_MS;
+    }
+
+    /// 保存用の1行に変換する。項目はタブで区切る
+    fn to_line(&self) -> String {
+        let mode = if self.mode == InputMode::Latin { "latin" } else { "japanese" };
+        format!(
+            "{}\t{}\t{}\t{:.3}\t{}\t{}\t{}",
+            sanitize_field(&self.problem),
+            sanitize_field(&self.word),
+            mode,
+            self.ease,
+            self.interval,
+            self.repetitions,
+            self.due
+        )
+    }
+
+    /// `to_line` で保存した1行から読み込む
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        Some(ReviewItem {
+            problem: fields.next()?.to_string(),
+            word: fields.next()?.to_string(),
+            mode: match fields.next()? {
+                "latin" => InputMode::Latin,
+                _ => InputMode::Japanese,
+            },
+            ease: fields.next()?.parse().ok()?,
+            interval: fields.next()?.parse().ok()?,
+            repetitions: fields.next()?.parse().ok()?,
+            due: fields.next()?.parse().ok()?,
+        })
+    }
+}
+
+/// 単語のミスの回数を、SM-2の入力の出来（0〜5）に変換する
+pub fn quality_for_misses(misses: usize) -> u32 {
+    match misses {
+        0 => 5,
+        1 => 3,
+        2 => 2,
+        _ => 1,
+    }
+}
+
+/// 復習の予定の一覧
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReviewQueue {
+    pub items: Vec<ReviewItem>,
+}
+
+impl ReviewQueue {
+    /// `to_text` で保存したテキストから読み込む。読めない行は無視する
+    pub fn from_text(text: &str) -> Self {
+        ReviewQueue { items: text.lines().filter_map(ReviewItem::from_line).collect() }
+    }
+
+    /// 保存用のテキストに変換する
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for item in &self.items {
+            text.push_str(&item.to_line());
+            text.push('\n');
+        }
+        text
+    }
+
+    /// 単語を入力した結果を記録する
+    ///
+    /// 予定にある単語は出来に応じて次の予定を決め直す。予定にない単語は、間違えた場合だけすぐに出題する項目として加える。
+    pub fn record(&mut self, problem: &str, word: &str, mode: InputMode, misses: usize, now: f64) {
+        if let Some(item) = self.items.iter_mut().find(|item| item.problem == problem && item.word == word) {
+            item.schedule(quality_for_misses(misses), now);
+        } else if misses > 0 {
+            self.items.push(ReviewItem {
+                problem: problem.to_string(),
+                word: word.to_string(),
+                mode,
+                ease: INITIAL_EASE,
+                interval: 0,
+                repetitions: 0,
+                due: now,
+            });
+        }
+    }
+
+    /// 完了したセッションの単語ごとのミスを記録する
+    ///
+    /// `problem_for_line` は行の番号から出題元の問題の名前を返す。
+    /// 同じ単語を何度も入力した場合は、最もミスの多かった回で記録する。
+    pub fn record_result(&mut self, model: &TypingModel, problem_for_line: impl Fn(usize) -> String, now: f64) {
+        let mode = if model.mode == InputMode::Latin { InputMode::Latin } else { InputMode::Japanese };
+        let mut words: Vec<(String, String, usize)> = Vec::new();
+        for (line_idx, word_idx) in typing::completed_word_positions(model) {
+            let word = &model.content.lines[line_idx].words[word_idx];
+            if typing::is_space_word(word) {
+                continue;
+            }
+            let Some(correctness) = model.typing_correctness.lines.get(line_idx).and_then(|line| line.words.get(word_idx)) else {
+                continue;
+            };
+            let misses = correctness
+                .segments
+                .iter()
+                .flat_map(|segment| &segment.chars)
+                .filter(|c| **c == TypingCorrectnessChar::Incorrect)
+                .count();
+            let problem = problem_for_line(line_idx);
+            let text = serializer::serialize_word_text(word);
+            match words.iter_mut().find(|(p, w, _)| *p == problem && *w == text) {
+                Some(entry) => entry.2 = entry.2.max(misses),
+                None => words.push((problem, text, misses)),
+            }
+        }
+        for (problem, word, misses) in words {
+            self.record(&problem, &word, mode, misses, now);
+        }
+    }
+
+    /// 出題する時刻を過ぎた項目を、予定の早い順に返す
+    pub fn due_items(&self, now: f64) -> Vec<&ReviewItem> {
+        let mut items: Vec<&ReviewItem> = self.items.iter().filter(|item| item.due <= now).collect();
+        items.sort_by(|a, b| a.due.total_cmp(&b.due));
+        items
+    }
+}
+
+/// 復習する単語を1行に1つずつ並べた問題を作る
+///
+/// 入力方式の混在を避けるため、最初の項目と同じ入力方式の項目だけを使う。
+/// 問題と一緒に、各行の出題元の問題の名前を返す。
+pub fn review_content(items: &[&ReviewItem]) -> (Content, Vec<String>) {
+    let mode = items.first().map_or(InputMode::Japanese, |item| item.mode);
+    let items: Vec<&&ReviewItem> = items.iter().filter(|item| item.mode == mode).take(REVIEW_SESSION_SIZE).collect();
+    let mut text = String::from("#title Review");
+    text.push_str(if mode == InputMode::Latin { "\n#mode latin" } else { "\n#mode japanese" });
+    let mut problems = Vec::new();
+    for item in &items {
+        // 本文の最初の行が `#` で始まると設定の指定と区別できないため、エスケープする
+        text.push('\n');
+        if problems.is_empty() && item.word.starts_with('#') {
+            text.push('\\');
+        }
+        text.push_str(&item.word);
+        problems.push(item.problem.clone());
+    }
+    (parser::parse_problem(&text), problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_review_schedule() {
+        // 間違えた単語がすぐに出題され、正しく入力するたびに間隔が延びることを確認
+        let mut queue = ReviewQueue::default();
+        queue.record("poems", "(花/はな)", InputMode::Japanese, 0, 0.0);
+        assert!(queue.items.is_empty());
+        queue.record("poems", "(花/はな)", InputMode::Japanese, 2, 0.0);
+        assert_eq!(queue.due_items(0.0).len(), 1);
+
+        let intervals: Vec<u32> = (0..4)
+            .map(|_| {
+                queue.record("poems", "(花/はな)", InputMode::Japanese, 0, 0.0);
+                queue.items[0].interval
+            })
+            .collect();
+        assert_eq!(intervals, vec![1, 6, 16, 45]);
+        assert!(queue.due_items(DAY_MS).is_empty());
+
+        // 再び間違えると間隔は1日に戻り、易しさが下がる
+        let ease = queue.items[0].ease;
+        queue.record("poems", "(花/はな)", InputMode::Japanese, 3, 0.0);
+        assert_eq!((queue.items[0].interval, queue.items[0].repetitions), (1, 0));
+        assert!(queue.items[0].ease < ease);
+
+        let restored = ReviewQueue::from_text(&format!("{}broken\n", queue.to_text()));
+        assert_eq!(restored.items.len(), 1);
+        assert_eq!(restored.items[0].word, "(花/はな)");
+        assert_eq!(restored.items[0].due, queue.items[0].due);
+
+        let (content, problems) = review_content(&restored.due_items(2.0 * DAY_MS));
+        assert_eq!(content.lines.len(), 1);
+        assert_eq!(problems, vec!["poems".to_string()]);
+        assert_eq!(content.options.mode, InputMode::Japanese);
+    }
+}
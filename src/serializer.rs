@@ -0,0 +1,279 @@
+// src/serializer.rs
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::string::String;
+#[cfg(not(feature = "uefi"))]
+use std::string::String;
+
+use crate::model::{Content, InputMode, Line, ProblemOptions, Segment, Word};
+
+/// `tokenize_line` が特別扱いする文字。テキストとして出力する際は `\` でエスケープする
+const ESCAPED_CHARS: [char; 7] = ['\\', '(', ')', '/', '-', ' ', '|'];
+
+/// 文字列をエスケープして出力先に追記する
+fn push_escaped(out: &mut String, text: &str) {
+    for c in text.chars() {
+        if ESCAPED_CHARS.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+}
+
+/// 単語が空白1文字だけで構成されているか（パーサーが `Token::Space` から生成する単語か）
+fn is_space_word(word: &Word) -> bool {
+    matches!(word.segments.as_slice(), [Segment::Plain { text }] if text == " ")
+}
+
+/// 1つの単語を出力する。単語内のセグメントはハイフンで連結する
+fn serialize_word(out: &mut String, word: &Word) {
+    for (i, segment) in word.segments.iter().enumerate() {
+        if i > 0 {
+            out.push('-');
+        }
+        match segment {
+            Segment::Plain { text } => push_escaped(out, text),
+            Segment::Annotated { base, reading } => {
+                out.push('(');
+                push_escaped(out, base);
+                out.push('/');
+                push_escaped(out, reading);
+                out.push(')');
+            }
+        }
+    }
+}
+
+/// 1つの単語を、問題ファイルの1行として読み込める形式のテキストに変換する
+pub fn serialize_word_text(word: &Word) -> String {
+    let mut out = String::new();
+    serialize_word(&mut out, word);
+    out
+}
+
+/// 1行を出力する
+///
+/// 単語同士は `/` で区切るが、空白だけの単語はそれ自体が区切りとして働くため、
+/// `escape_spaces` が偽の場合は前後に `/` を置かずそのまま空白として出力する。
+/// `escape_spaces` が真の場合は空白の単語も `\ ` として出力し、行頭・行末の空白が
+/// トリムされても単語が失われないようにする。
+/// 上の句と下の句の区切りは `|` として出力する。`|` も単語の区切りとして働くため、前後に `/` は置かない。
+fn serialize_line(line: &Line, escape_spaces: bool) -> String {
+    let mut out = String::new();
+    let mut previous_was_space = true;
+    for (index, word) in line.words.iter().enumerate() {
+        if line.split == Some(index) {
+            out.push('|');
+            previous_was_space = true;
+        }
+        if is_space_word(word) && !escape_spaces {
+            out.push(' ');
+            previous_was_space = true;
+            continue;
+        }
+        if !previous_was_space {
+            out.push('/');
+        }
+        serialize_word(&mut out, word);
+        previous_was_space = false;
+    }
+    if line.split == Some(line.words.len()) {
+        out.push('|');
+    }
+    out
+}
+
+/// 行頭・行末の空白がトリムで失われないように保護する
+///
+/// 行頭の空白文字はエスケープし、行末の空白文字の後ろには空の区切り `/` を置く。
+fn protect_edges(mut text: String) -> String {
+    if text.starts_with(char::is_whitespace) {
+        text.insert(0, '\\');
+    }
+    if text.ends_with(char::is_whitespace) {
+        text.push('/');
+    }
+    text
+}
+
+/// 既定値と異なる設定を `#` 指定の行として出力する
+fn serialize_options(out: &mut String, options: &ProblemOptions) {
+    match options.mode {
+        InputMode::Auto => {}
+        InputMode::Japanese => out.push_str("\n#mode japanese"),
+        InputMode::Latin => out.push_str("\n#mode latin"),
+    }
+    let rules = &options.latin_rules;
+    if rules.ignore_case {
+        out.push_str("\n#case ignore");
+    }
+    if rules.loose_punctuation {
+        out.push_str("\n#punctuation loose");
+    }
+    if rules.collapse_spaces {
+        out.push_str("\n#spacing collapse");
+    }
+}
+
+/// `Content` を `.ntq` 形式のテキストに変換する
+///
+/// 出力は `parser::parse_problem` で読み込むと元の `Content` に戻る。
+/// ただし `.ntq` で表現できない空の行（単語を持たない行）は出力されない。
+pub fn serialize_problem(content: &Content) -> String {
+    let mut out = String::from("#title");
+
+    if !content.title.words.is_empty() {
+        out.push(' ');
+        // タイトル行は前後の空白がトリムされるため、端に空白の単語がある場合はエスケープする
+        let has_edge_space = content.title.words.first().is_some_and(is_space_word)
+            || content.title.words.last().is_some_and(is_space_word);
+        out.push_str(&protect_edges(serialize_line(&content.title, has_edge_space)));
+    }
+
+    serialize_options(&mut out, &content.options);
+
+    let mut is_first_line = true;
+    for line in &content.lines {
+        if line.words.is_empty() {
+            continue;
+        }
+        out.push('\n');
+        // 空白だけの行は読み込み時に読み飛ばされるため、エスケープして残す
+        let mut text = serialize_line(line, false);
+        if text.trim().is_empty() {
+            text = protect_edges(serialize_line(line, true));
+        }
+        // 本文の最初の行が `#` で始まると設定の指定と区別できないため、エスケープする
+        if is_first_line && text.starts_with('#') {
+            out.push('\\');
+        }
+        out.push_str(&text);
+        is_first_line = false;
+    }
+
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::LatinRules;
+    use crate::parser::parse_problem;
+    use crate::random::Random;
+
+    /// エスケープ対象の記号を多めに含む文字集合
+    const ALPHABET: [char; 16] = ['あ', 'ん', '漢', 'ア', 'a', 'Z', '1', '(', ')', '/', '-', '\\', ' ', '#', '　', '、'];
+
+    fn gen_text(rng: &mut Random, min_len: usize) -> String {
+        let len = min_len + rng.below(4);
+        (0..len).map(|_| ALPHABET[rng.below(ALPHABET.len())]).collect()
+    }
+
+    fn gen_segment(rng: &mut Random) -> Segment {
+        if rng.below(2) == 0 {
+            Segment::Plain { text: gen_text(rng, 1) }
+        } else {
+            Segment::Annotated { base: gen_text(rng, 0), reading: gen_text(rng, 0) }
+        }
+    }
+
+    fn gen_word(rng: &mut Random) -> Word {
+        if rng.below(5) == 0 {
+            return Word { segments: vec![Segment::Plain { text: " ".to_string() }] };
+        }
+        let count = 1 + rng.below(3);
+        Word { segments: (0..count).map(|_| gen_segment(rng)).collect() }
+    }
+
+    fn gen_line(rng: &mut Random, min_words: usize) -> Line {
+        let count = min_words + rng.below(6);
+        Line { words: (0..count).map(|_| gen_word(rng)).collect(), split: None }
+    }
+
+    fn gen_body_line(rng: &mut Random) -> Line {
+        let mut line = gen_line(rng, 1);
+        if rng.below(3) == 0 {
+            line.split = Some(rng.below(line.words.len() + 1));
+        }
+        line
+    }
+
+    fn gen_options(rng: &mut Random) -> ProblemOptions {
+        ProblemOptions {
+            mode: [InputMode::Auto, InputMode::Japanese, InputMode::Latin][rng.below(3)],
+            latin_rules: LatinRules {
+                ignore_case: rng.below(2) == 0,
+                loose_punctuation: rng.below(2) == 0,
+                collapse_spaces: rng.below(2) == 0,
+            },
+        }
+    }
+
+    fn gen_content(rng: &mut Random) -> Content {
+        let line_count = rng.below(5);
+        Content {
+            title: gen_line(rng, 0),
+            lines: (0..line_count).map(|_| gen_body_line(rng)).collect(),
+            options: gen_options(rng),
+        }
+    }
+
+    #[test]
+    fn test_round_trip_generated_contents() {
+        // 生成した任意の Content が、シリアライズ→パースで元に戻ることを確認
+        let mut rng = Random::new(0x9E37_79B9_7F4A_7C15);
+        for case in 0..2000 {
+            let content = gen_content(&mut rng);
+            let text = serialize_problem(&content);
+            assert_eq!(parse_problem(&text), content, "case {} failed:\n{}", case, text);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_examples() {
+        // 同梱の問題ファイルが、読み込み→シリアライズ→パースで同じ内容になることを確認
+        for index in 0..crate::app::PROBLEM_FILES_NAMES.len() {
+            let content = crate::app::load_problem(index);
+            assert_eq!(parse_problem(&serialize_problem(&content)), content);
+        }
+    }
+
+    #[test]
+    fn test_serialize_readable_output() {
+        // 通常の問題文は手書きの .ntq と同じ形で出力されることを確認
+        let content = parse_problem("#title (百人一首/ひゃくにんいっしゅ)\n(思/おも)-ひ-(絶/た)-え/なむ (春/はる)\n");
+        assert_eq!(
+            serialize_problem(&content),
+            "#title (百人一首/ひゃくにんいっしゅ)\n(思/おも)-ひ-(絶/た)-え/なむ (春/はる)\n"
+        );
+    }
+
+    #[test]
+    fn test_serialize_escapes_special_chars() {
+        // 特殊文字がエスケープされることを確認
+        let content = Content {
+            title: Line { words: Vec::new(), split: None },
+            lines: vec![Line { words: vec![
+                Word { segments: vec![Segment::Plain { text: "(c) a-b".to_string() }] },
+                Word { segments: vec![Segment::Annotated { base: "A/B".to_string(), reading: "C:\\".to_string() }] },
+            ], split: None }],
+            options: ProblemOptions::default(),
+        };
+        assert_eq!(serialize_problem(&content), "#title\n\\(c\\)\\ a\\-b/(A\\/B/C:\\\\)\n");
+    }
+
+    #[test]
+    fn test_serialize_options_and_leading_hash() {
+        // 設定がタイトルの直後に出力され、`#` で始まる本文の先頭行がエスケープされることを確認
+        let mut content = parse_problem("#title x\n\\#mode latin\n#2");
+        content.options.mode = InputMode::Latin;
+        content.options.latin_rules.collapse_spaces = true;
+        let text = serialize_problem(&content);
+        assert_eq!(text, "#title x\n#mode latin\n#spacing collapse\n\\#mode latin\n#2\n");
+        assert_eq!(parse_problem(&text), content);
+    }
+}
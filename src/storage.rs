@@ -0,0 +1,63 @@
+// src/storage.rs
+
+// 学習データなど、セッションをまたいで残すテキストを保存・読み込みする
+// デスクトップではファイル、WASMではlocalStorageを使い、UEFIでは保存しない
+
+#[cfg(feature = "uefi")]
+extern crate alloc;
+
+#[cfg(feature = "uefi")]
+use alloc::string::String;
+#[cfg(not(feature = "uefi"))]
+use std::string::String;
+
+/// タブ区切りで保存する1行を壊さないよう、項目の中のタブと改行を空白に置き換える
+pub(crate) fn sanitize_field(text: &str) -> String {
+    text.chars().map(|c| if c == '\t' || c == '\n' || c == '\r' { ' ' } else { c }).collect()
+}
+
+/// 保存先のディレクトリ（作業ディレクトリからの相対パス）
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "uefi")))]
+pub const STORAGE_DIR: &str = ".typingmp";
+
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "uefi")))]
+fn storage_path(key: &str) -> std::path::PathBuf {
+    std::path::Path::new(STORAGE_DIR).join(format!("{}.txt", key))
+}
+
+/// `key` で保存したテキストを読み込む。保存されていなければ `None`
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "uefi")))]
+pub fn load(key: &str) -> Option<String> {
+    std::fs::read_to_string(storage_path(key)).ok()
+}
+
+/// テキストを `key` で保存する。保存できたかを返す
+#[cfg(all(not(target_arch = "wasm32"), not(feature = "uefi")))]
+pub fn save(key: &str, text: &str) -> bool {
+    std::fs::create_dir_all(STORAGE_DIR).is_ok() && std::fs::write(storage_path(key), text).is_ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load(key: &str) -> Option<String> {
+    local_storage()?.get_item(&format!("typingmp.{}", key)).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save(key: &str, text: &str) -> bool {
+    local_storage().is_some_and(|storage| storage.set_item(&format!("typingmp.{}", key), text).is_ok())
+}
+
+#[cfg(feature = "uefi")]
+pub fn load(_key: &str) -> Option<String> {
+    None
+}
+
+#[cfg(feature = "uefi")]
+pub fn save(_key: &str, _text: &str) -> bool {
+    false
+}
@@ -3,9 +3,11 @@
 #[cfg(not(feature = "uefi"))]
 use crate::app::{App, AppEvent, Fonts, TuiDisplayMode};
 #[cfg(not(feature = "uefi"))]
+use crate::layout_data;
+#[cfg(not(feature = "uefi"))]
 use crate::model::Segment;
 #[cfg(not(feature = "uefi"))]
-use crate::renderer::{gui_renderer, tui_renderer}; // gui_renderer をインポート
+use crate::renderer::{format_chart_value, gui_renderer, tui_renderer}; // gui_renderer をインポート
 #[cfg(not(feature = "uefi"))]
 use crate::ui::{
     self, ActiveLowerElement, Align, Anchor, FontSize, HorizontalAlign, LowerTypingSegment,
@@ -168,6 +170,12 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                                 });
 
                                 let (art_buffer, art_width, _, char_ascent) = renderer(current_font, &seg.base_text, render_font_size);
+                                if seg.hidden {
+                                    // 伏せた文字の代わりに、文字の幅だけ下線を引く
+                                    draw_plain_text_at(&mut current_buffer, &"_".repeat(art_width), pen_x, line_baseline_y, cols, u32_to_crossterm_color(ui::PENDING_COLOR));
+                                    pen_x += art_width as i32;
+                                    continue;
+                                }
                                 let blit_y = line_baseline_y - char_ascent as i32;
                                 blit_art(&mut current_buffer, cols, rows, &art_buffer, art_width, 0, pen_x as isize, blit_y as isize, color);
 
@@ -202,8 +210,13 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                                     ui::UpperSegmentState::Active => ui::ACTIVE_COLOR,
                                     ui::UpperSegmentState::Pending => ui::PENDING_COLOR,
                                 });
-                                draw_plain_text_at(&mut current_buffer, &seg.base_text, pen_x, pen_y, cols, color);
-                                pen_x += seg.base_text.chars().count() as i32;
+                                let char_count = seg.base_text.chars().count();
+                                if seg.hidden {
+                                    draw_plain_text_at(&mut current_buffer, &"_".repeat(char_count), pen_x, pen_y, cols, u32_to_crossterm_color(ui::PENDING_COLOR));
+                                } else {
+                                    draw_plain_text_at(&mut current_buffer, &seg.base_text, pen_x, pen_y, cols, color);
+                                }
+                                pen_x += char_count as i32;
                             }
                         }
                     }
@@ -360,6 +373,59 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                 }
+                Renderable::LineChart { series, x_max, x_label, anchor, shift, align, width_ratio, height_ratio, font_size: _ } => {
+                    // 1行目に凡例、下2行に横軸と目盛りを置き、残りを点字で描くグラフの領域にする
+                    let chart_w = (cols as f32 * width_ratio) as usize;
+                    let chart_h = ((rows as f32 * height_ratio) as usize).max(5);
+                    let anchor_pos = ui::calculate_anchor_position(anchor, shift, cols, rows);
+                    let (x0, y0) = ui::calculate_aligned_position(anchor_pos, chart_w as u32, chart_h as u32, align);
+                    let y_labels: Vec<String> = series.iter().take(2).map(|s| format_chart_value(s.y_max)).collect();
+                    let left_w = y_labels.first().map_or(0, |label| label.chars().count()) + 1;
+                    let right_w = y_labels.get(1).map_or(0, |label| label.chars().count() + 1);
+                    if chart_w <= left_w + right_w + 1 { continue; }
+                    let plot_w = chart_w - left_w - right_w;
+                    let plot_h = chart_h - 3;
+                    let plot_x = x0 + left_w as i32;
+                    let axis_color = u32_to_crossterm_color(ui::CHART_AXIS_COLOR);
+
+                    let mut legend_x = plot_x;
+                    for s in &series {
+                        let legend = format!("■ {}", s.label);
+                        draw_plain_text_at(&mut current_buffer, &legend, legend_x, y0, cols, u32_to_crossterm_color(s.color));
+                        legend_x += legend.chars().count() as i32 + 2;
+                    }
+
+                    for row in 1..=plot_h as i32 {
+                        draw_plain_text_at(&mut current_buffer, "│", plot_x - 1, y0 + row, cols, axis_color);
+                        if series.len() > 1 {
+                            draw_plain_text_at(&mut current_buffer, "│", plot_x + plot_w as i32, y0 + row, cols, axis_color);
+                        }
+                    }
+                    let axis_y = y0 + plot_h as i32 + 1;
+                    let mut axis_line = "└".to_string() + &"─".repeat(plot_w);
+                    if series.len() > 1 { axis_line.push('┘'); }
+                    draw_plain_text_at(&mut current_buffer, &axis_line, plot_x - 1, axis_y, cols, axis_color);
+
+                    if let Some(label) = y_labels.first() {
+                        draw_plain_text_at(&mut current_buffer, label, plot_x - 1 - label.chars().count() as i32, y0 + 1, cols, u32_to_crossterm_color(series[0].color));
+                        draw_plain_text_at(&mut current_buffer, "0", plot_x - 2, y0 + plot_h as i32, cols, axis_color);
+                    }
+                    if let Some(label) = y_labels.get(1) {
+                        draw_plain_text_at(&mut current_buffer, label, plot_x + plot_w as i32 + 1, y0 + 1, cols, u32_to_crossterm_color(series[1].color));
+                    }
+                    let x_max_label = format!("{}s", format_chart_value(x_max.round()));
+                    draw_plain_text_at(&mut current_buffer, "0", plot_x, axis_y + 1, cols, axis_color);
+                    draw_plain_text_at(&mut current_buffer, &x_max_label, plot_x + plot_w as i32 - x_max_label.chars().count() as i32, axis_y + 1, cols, axis_color);
+                    draw_plain_text_at(&mut current_buffer, &x_label, plot_x + (plot_w as i32 - x_label.chars().count() as i32) / 2, axis_y + 1, cols, axis_color);
+
+                    let normalized: Vec<Vec<(f32, f32)>> = series.iter().map(|s| s.normalized_points(x_max)).collect();
+                    let (chars, owners) = tui_renderer::render_line_chart_to_braille(&normalized, plot_w, plot_h);
+                    for (i, (c, owner)) in chars.iter().zip(owners.iter()).enumerate() {
+                        let Some(index) = owner else { continue };
+                        let color = u32_to_crossterm_color(series[*index].color);
+                        draw_plain_text_at(&mut current_buffer, &c.to_string(), plot_x + (i % plot_w) as i32, y0 + 1 + (i / plot_w) as i32, cols, color);
+                    }
+                }
             }
         }
 
@@ -369,6 +435,9 @@ pub fn run() -> Result<(), Box<dyn std::error::Error>> {
         previous_state = app.state;
 
         handle_input(&mut app)?;
+
+        // 結果のHTML書き出しが要求されていればファイルに保存する
+        app.flush_pending_export(|file_name, html| std::fs::write(file_name, html).map_err(|e| e.to_string()));
     }
 
     execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
@@ -548,7 +617,11 @@ fn handle_input(app: &mut App) -> std::io::Result<()> {
         if let Event::Key(key) = event::read()? {
             if key.kind == KeyEventKind::Press {
                 match key.code {
-                    KeyCode::Char(c) => app.on_event(AppEvent::Char { c, timestamp: crate::timestamp::now() }),
+                    KeyCode::Char(c) => {
+                        // JISかな配列では、ターミナルから届いた英数字をキーに対応するかなに置き換える
+                        let c = if app.uses_jis_kana_keys() { layout_data::jis_kana_for_ascii(c).unwrap_or(c) } else { c };
+                        app.on_event(AppEvent::Char { c, timestamp: crate::timestamp::now() })
+                    }
                     KeyCode::Backspace => app.on_event(AppEvent::Backspace),
                     KeyCode::Up => app.on_event(AppEvent::Up),
                     KeyCode::Down => app.on_event(AppEvent::Down),
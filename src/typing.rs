@@ -7,6 +7,7 @@ extern crate alloc;
 use alloc::{
     format,
     string::{String, ToString},
+    vec,
     vec::Vec,
 };
 #[cfg(not(feature = "uefi"))]
@@ -15,11 +16,19 @@ use std::{
     vec::Vec,
 };
 
+use crate::layout_data;
 use crate::model::{
-    Content, Model, ResultModel, Segment, TypingCorrectnessChar, TypingCorrectnessContent,
+    Content, ErrorPolicy, InputMode, KeystrokeEfficiency, LatinRules, Layout, Line, Model, ResultModel, Segment, SessionOptions, SpellingPreferences, SpellingStats, Word, TypingCorrectnessChar, TypingCorrectnessContent,
     TypingCorrectnessLine, TypingCorrectnessSegment, TypingCorrectnessWord, TypingInput, TypingMetrics, TypingModel,
     TypingSession,
 };
+use crate::random::Random;
+
+/// この時間（ミリ秒）より長く入力が途切れた場合は一時停止とみなし、新しいセッションを始める
+pub const SESSION_GAP_MS: f64 = 1000.0;
+
+/// 直近の速度を求める時間の幅（ミリ秒）
+pub const ROLLING_WINDOW_MS: f64 = 10000.0;
 
 // Helper function for logging to handle both native and wasm targets.
 fn log(_message: &str) {
@@ -42,6 +51,279 @@ fn log(_message: &str) {
     }
 }
 
+/// 問題の入力方式を決定する
+///
+/// `#mode` の指定がない場合、ルビを含まず本文がすべてラテン文字と記号で書かれていれば英字モードとする。
+pub fn resolve_input_mode(content: &Content) -> InputMode {
+    match content.options.mode {
+        InputMode::Auto => {
+            let is_latin_text = !content.lines.is_empty()
+                && content.lines.iter().flat_map(|line| &line.words).flat_map(|word| &word.segments).all(|segment| match segment {
+                    Segment::Plain { text } => text.chars().all(is_latin_char),
+                    Segment::Annotated { .. } => false,
+                });
+            if is_latin_text { InputMode::Latin } else { InputMode::Japanese }
+        }
+        mode => mode,
+    }
+}
+
+/// ラテン文字（拡張ラテン文字を含む）、ASCII記号、一般句読点のいずれかであるか
+fn is_latin_char(c: char) -> bool {
+    c < '\u{0250}' || ('\u{2000}'..='\u{206F}').contains(&c)
+}
+
+/// 曲がった引用符やダッシュなどを、キーボードで入力できるASCII記号に置き換える
+fn to_ascii_punctuation(c: char) -> char {
+    match c {
+        '‘' | '’' | '‚' | '′' => '\'',
+        '“' | '”' | '„' | '″' => '"',
+        '‐' | '‑' | '‒' | '–' | '—' | '―' | '−' => '-',
+        '\u{00A0}' => ' ',
+        _ => c,
+    }
+}
+
+/// 英字モードで、入力した文字が問題文の文字と一致するかを照合規則に従って判定する
+fn latin_chars_match(input: char, target: char, rules: &LatinRules) -> bool {
+    let (input, target) = if rules.loose_punctuation {
+        (to_ascii_punctuation(input), to_ascii_punctuation(target))
+    } else {
+        (input, target)
+    };
+    if rules.ignore_case {
+        input.to_lowercase().eq(target.to_lowercase())
+    } else {
+        input == target
+    }
+}
+
+/// 空白だけで構成された単語か
+pub(crate) fn is_space_word(word: &Word) -> bool {
+    word.segments.iter().all(|segment| matches!(segment, Segment::Plain { text } if text.chars().all(char::is_whitespace)))
+}
+
+/// 片仮名を対応する平仮名に変換する。`ー` のような対応する平仮名がない文字はそのまま返す
+///
+/// 1文字ずつ置き換えるため、変換の前後で文字数は変わらない。
+pub(crate) fn to_hiragana(c: char) -> char {
+    if ('ァ'..='ヶ').contains(&c) {
+        core::char::from_u32(c as u32 - 0x60).unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// 打った綴りがヘボン式・訓令式のどちらに特有のものかを記録する
+fn record_spelling(stats: &mut SpellingStats, romaji: &str) {
+    if layout_data::is_hepburn_spelling(romaji) {
+        stats.hepburn += 1;
+    } else if layout_data::is_kunrei_spelling(romaji) {
+        stats.kunrei += 1;
+    }
+}
+
+/// 綴りの記録から、利用者が自然に使っているローマ字の方式を返す。差がない場合は `None`
+pub fn preferred_spelling(stats: &SpellingStats) -> Option<&'static str> {
+    match stats.hepburn.cmp(&stats.kunrei) {
+        core::cmp::Ordering::Greater => Some("Hepburn"),
+        core::cmp::Ordering::Less => Some("Kunrei"),
+        core::cmp::Ordering::Equal => None,
+    }
+}
+
+/// 読みを入力するためのキー列を組み立てる
+///
+/// 先頭から、配列で一度に確定できる最も長いかなを選んでいく。綴りは利用者が最もよく使うものを優先し、
+/// 記録がなければ最も短いものを選ぶ。`typed` は先頭のかなについて入力済みの未確定の文字列で、
+/// これと食い違う綴りは選ばない。配列に含まれない文字はそのまま出力する。
+pub fn romaji_for_reading(reading: &str, layout: &Layout, preferences: &SpellingPreferences, typed: &str) -> String {
+    let lookup = reading.chars().map(to_hiragana).collect::<String>();
+    let mut rest = lookup.as_str();
+    let mut out = String::new();
+    let mut typed = typed;
+    while !rest.is_empty() {
+        let candidate = layout
+            .mapping
+            .iter()
+            .filter(|(key, _)| rest.starts_with(key.as_str()))
+            .filter_map(|(key, values)| {
+                let usable: Vec<String> = values.iter().filter(|v| !v.is_empty() && v.starts_with(typed)).cloned().collect();
+                (!usable.is_empty()).then_some((key, usable))
+            })
+            .max_by_key(|(key, _)| key.chars().count());
+        match candidate {
+            Some((key, usable)) => {
+                let spelling = preferences
+                    .preferred(key, &usable)
+                    .or_else(|| usable.iter().min_by_key(|v| v.chars().count()))
+                    .cloned()
+                    .unwrap_or_default();
+                out.push_str(&spelling);
+                rest = &rest[key.len()..];
+            }
+            None => {
+                let c = rest.chars().next().unwrap_or_default();
+                out.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+        typed = "";
+    }
+    out
+}
+
+/// 現在の単語の残りを入力するためのキー列（ローマ字のヒント）を返す。英字モードでは `None`
+pub fn romaji_hint(model: &TypingModel) -> Option<String> {
+    if model.mode == InputMode::Latin {
+        return None;
+    }
+    let word = model.content.lines.get(model.status.line as usize)?.words.get(model.status.word as usize)?;
+    let reading: String = word.segments[model.status.segment as usize..]
+        .iter()
+        .map(|seg| match seg {
+            Segment::Plain { text } => text.as_str(),
+            Segment::Annotated { reading, .. } => reading.as_str(),
+        })
+        .collect::<String>()
+        .chars()
+        .skip(model.status.char_ as usize)
+        .collect();
+    let typed: String = model.status.unconfirmed.iter().collect();
+    Some(romaji_for_reading(&reading, &model.layout, &model.preferences, &typed))
+}
+
+/// 単語の読みを連結する
+pub(crate) fn word_reading(word: &Word) -> String {
+    word.segments
+        .iter()
+        .map(|seg| match seg {
+            Segment::Plain { text } => text.as_str(),
+            Segment::Annotated { reading, .. } => reading.as_str(),
+        })
+        .collect()
+}
+
+/// 単語を、利用者の綴りで入力した場合の打鍵数
+fn preferred_keystrokes_for_word(model: &TypingModel, word: &Word) -> usize {
+    let reading = word_reading(word);
+    if model.mode == InputMode::Latin {
+        reading.chars().count()
+    } else {
+        romaji_for_reading(&reading, &model.layout, &model.preferences, "").chars().count()
+    }
+}
+
+/// 入力を終えた単語の (行, 単語) の位置。時間切れで終わったセッションでは、途中の単語は含まない
+pub(crate) fn completed_word_positions(model: &TypingModel) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let current = model.status.line as usize;
+    model.content.lines.iter().enumerate().take(current + 1).flat_map(move |(line_idx, line)| {
+        let word_count = if line_idx < current { line.words.len() } else { (model.status.word as usize).min(line.words.len()) };
+        (0..word_count).map(move |word_idx| (line_idx, word_idx))
+    })
+}
+
+/// 入力を終えた単語
+fn completed_words(model: &TypingModel) -> impl Iterator<Item = &Word> {
+    completed_word_positions(model).map(|(line, word)| &model.content.lines[line].words[word])
+}
+
+/// 読みを配列で入力するのに必要な最少の打鍵数
+///
+/// かなの区切り方ごとの打鍵数を後ろから求める（`xtu` + `ka` より `kka` が短い、など）。
+/// 配列に含まれない文字は1打鍵として数える。
+pub fn minimal_keystrokes_for_reading(reading: &str, layout: &Layout) -> usize {
+    let lookup = reading.chars().map(to_hiragana).collect::<String>();
+    let boundaries: Vec<usize> = lookup.char_indices().map(|(i, _)| i).chain(core::iter::once(lookup.len())).collect();
+    // costs[i] は boundaries[i] から末尾までの最少打鍵数
+    let mut costs = vec![0usize; boundaries.len()];
+    for i in (0..boundaries.len() - 1).rev() {
+        let rest = &lookup[boundaries[i]..];
+        let mut best = None;
+        for (key, values) in &layout.mapping {
+            if key.is_empty() || !rest.starts_with(key.as_str()) {
+                continue;
+            }
+            if let Some(shortest) = values.iter().filter(|v| !v.is_empty()).map(|v| v.chars().count()).min() {
+                let next = boundaries.partition_point(|&b| b < boundaries[i] + key.len());
+                let cost = shortest + costs[next];
+                best = Some(best.map_or(cost, |best: usize| best.min(cost)));
+            }
+        }
+        costs[i] = best.unwrap_or(1 + costs[i + 1]);
+    }
+    costs[0]
+}
+
+/// 完了したセッションについて、入力を終えた単語の打鍵の効率を求める
+pub fn keystroke_efficiency(model: &TypingModel) -> KeystrokeEfficiency {
+    let minimal = completed_words(model)
+        .map(|word| {
+            let reading = word_reading(word);
+            if model.mode == InputMode::Latin {
+                reading.chars().count()
+            } else {
+                minimal_keystrokes_for_reading(&reading, &model.layout)
+            }
+        })
+        .sum();
+    // 最少の打鍵数と同じく、途中まで入力した単語への打鍵は数えない
+    let current = (model.status.line, model.status.word);
+    let actual = model
+        .user_input
+        .iter()
+        .flat_map(|session| session.inputs.iter().map(move |input| (session.line, input)))
+        .filter(|(line, input)| input.is_correct && (*line, input.word) < current)
+        .count();
+    let ratio = if actual > 0 { minimal as f64 / actual as f64 } else { 0.0 };
+    let preferred = completed_words(model).map(|word| preferred_keystrokes_for_word(model, word)).sum();
+    KeystrokeEfficiency { minimal, preferred, actual, ratio }
+}
+
+/// セッションを終えて結果を作る
+pub fn finish_session(model: TypingModel) -> ResultModel {
+    ResultModel { efficiency: keystroke_efficiency(&model), typing_model: model }
+}
+
+/// 問題選択画面の設定に従って、入力する行を選ぶ
+///
+/// 開始行より前の行を除き、並べ替えを指定した場合は `seed` で並べ替えてから、指定した行数だけ残す。
+/// 開始行が行数を超える場合は最後の行から始める。
+pub fn apply_session_options(content: &Content, options: &SessionOptions, seed: u64) -> Content {
+    let start = options.start_line.saturating_sub(1).min(content.lines.len().saturating_sub(1));
+    let mut lines: Vec<Line> = content.lines.iter().skip(start).cloned().collect();
+    if options.shuffle {
+        Random::new(seed).shuffle(&mut lines);
+    }
+    if let Some(count) = options.line_count {
+        lines.truncate(count.max(1));
+    }
+    Content { title: content.title.clone(), lines, options: content.options }
+}
+
+/// 時間制限のある練習で、`min_chars` 文字以上になるまで問題の行を繰り返した問題を作る
+pub fn repeat_content(content: &Content, min_chars: usize) -> Content {
+    let lap_chars: usize = content.lines.iter().flat_map(|line| &line.words).map(|word| word_reading(word).chars().count()).sum();
+    let laps = min_chars.div_ceil(lap_chars.max(1)).max(1);
+    Content {
+        title: content.title.clone(),
+        lines: content.lines.iter().cycle().take(content.lines.len() * laps).cloned().collect(),
+        options: content.options,
+    }
+}
+
+/// 時間制限のある練習の残り時間（ミリ秒）。時間制限がなければ `None`
+///
+/// 制限時間はカウントダウンの終わりから数える。開始時刻がない場合は最初の打鍵から数える。
+pub fn time_remaining(model: &TypingModel, now: f64) -> Option<f64> {
+    let limit = model.time_limit?;
+    let start = model.start_time.or_else(|| all_inputs(model).next().map(|input| input.timestamp));
+    Some(match start {
+        Some(start) => (start + limit.duration - now).clamp(0.0, limit.duration),
+        None => limit.duration,
+    })
+}
+
 pub fn key_input(mut model: TypingModel, input: char, timestamp: f64) -> Model {
     log(&format!("\n--- key_input: '{}' --- typing.rs", input));
     log(&format!(
@@ -57,14 +339,16 @@ pub fn key_input(mut model: TypingModel, input: char, timestamp: f64) -> Model {
         return Model::Typing(model);
     }
 
+    // 一定時間以上入力が途切れた場合と、行が変わった場合に新しいセッションを始める
     if model
         .user_input
         .is_empty()
+        || model.user_input.last().is_some_and(|s| s.line != model.status.line)
         || model
             .user_input
             .last()
             .and_then(|s| s.inputs.last())
-            .map_or(true, |i| (current_time - i.timestamp) > 1000.0)
+            .map_or(true, |i| (current_time - i.timestamp) > SESSION_GAP_MS)
     {
         model.user_input.push(TypingSession {
             line: model.status.line,
@@ -91,55 +375,83 @@ pub fn key_input(mut model: TypingModel, input: char, timestamp: f64) -> Model {
     // 現在の文字位置から始まる部分文字列を取得
     let target_slice = target_reading.chars().skip(model.status.char_ as usize).collect::<String>();
 
-    fn normalize_char(c: char) -> char {
-        let lower = c.to_lowercase().next().unwrap_or(c);
-        if lower >= 'ァ' && lower <= 'ヶ' {
-            core::char::from_u32(lower as u32 - 0x60).unwrap_or(lower)
-        } else {
-            lower
-        }
-    }
+    let latin_rules = model.content.options.latin_rules;
+    let mut collapse_following_spaces = false;
 
-    // 1. フリック入力などによる直接の文字一致を優先
-    if let Some(target_char) = target_slice.chars().next() {
-        if normalize_char(input) == normalize_char(target_char) {
-            is_correct = true;
-            advance_chars = 1;
-            model.status.unconfirmed.clear();
+    if model.mode == InputMode::Latin {
+        // 英字モードでは、問題文の文字を1文字ずつそのまま照合する
+        if let Some(target_char) = target_slice.chars().next() {
+            if latin_chars_match(input, target_char, &latin_rules) {
+                is_correct = true;
+                advance_chars = 1;
+                if latin_rules.collapse_spaces && target_char.is_whitespace() {
+                    advance_chars = target_slice.chars().take_while(|c| c.is_whitespace()).count();
+                    collapse_following_spaces = true;
+                }
+            }
+        }
+    } else {
+        // 1. フリック入力などによる直接の文字一致を優先（平仮名と片仮名は区別する）
+        if let Some(target_char) = target_slice.chars().next() {
+            if input.to_lowercase().eq(target_char.to_lowercase()) {
+                is_correct = true;
+                advance_chars = 1;
+                model.status.unconfirmed.clear();
+            }
         }
-    }
 
-    // 2. 直接一致しない場合、ローマ字入力として処理を試みる
-    if !is_correct {
-        let mut expect = Vec::new();
-        for (key, values) in model.layout.mapping.iter() {
-            if target_slice.starts_with(key) {
-                for v in values {
-                    if v.starts_with(&model.status.unconfirmed.iter().collect::<String>()) {
-                        expect.push((key.clone(), (*v).to_string()));
+        // 2. 直接一致しない場合、ローマ字入力として処理を試みる
+        if !is_correct {
+            // ローマ字表は平仮名で引くため、片仮名の読みは平仮名に置き換えて照合する
+            let lookup_slice = target_slice.chars().map(to_hiragana).collect::<String>();
+            let mut expect = Vec::new();
+            for (key, values) in model.layout.mapping.iter() {
+                if lookup_slice.starts_with(key) {
+                    for v in values {
+                        if v.starts_with(&model.status.unconfirmed.iter().collect::<String>()) {
+                            expect.push((key.clone(), (*v).to_string()));
+                        }
                     }
                 }
             }
-        }
 
-        if !expect.is_empty() {
             let mut current_input_str = model.status.unconfirmed.iter().collect::<String>();
             current_input_str.push(input);
+            let lower_current_input_str = current_input_str.to_lowercase();
 
-            for (key, val_str) in expect {
-                let lower_val_str = val_str.to_lowercase();
-                let lower_current_input_str = current_input_str.to_lowercase();
+            if !expect.is_empty() {
 
-                if lower_val_str == lower_current_input_str {
+                // 入力と完全に一致する候補を優先する。複数あれば、より多くのかなを確定できる候補を選ぶ
+                // （AZIKの `kz` → 「かん」のように、1つの入力で複数のかなを確定する項目があるため）
+                let exact_match = expect
+                    .iter()
+                    .filter(|(_, val_str)| val_str.to_lowercase() == lower_current_input_str)
+                    .max_by_key(|(key, _)| key.chars().count());
+
+                if let Some((key, val_str)) = exact_match {
                     is_correct = true;
                     model.status.unconfirmed.clear();
                     advance_chars = key.chars().count();
-                    break;
-                } else if lower_val_str.starts_with(&lower_current_input_str) {
+                    record_spelling(&mut model.spelling, val_str);
+                    model.preferences.record(key, val_str);
+                } else if expect.iter().any(|(_, val_str)| val_str.to_lowercase().starts_with(&lower_current_input_str)) {
                     is_correct = true;
                     is_romaji_in_progress = true;
                     model.status.unconfirmed.push(input);
-                    break;
+                }
+            }
+
+            // 配列で許可されていない綴りを打ち始めた場合は、ミスとしたうえで綴りの使い分けに記録する
+            if !is_correct {
+                let rejected = model.layout.rejected.iter().find_map(|(key, values)| {
+                    if !lookup_slice.starts_with(key.as_str()) {
+                        return None;
+                    }
+                    values.iter().find(|v| v.starts_with(&lower_current_input_str))
+                });
+                if let Some(spelling) = rejected {
+                    model.spelling.rejected += 1;
+                    record_spelling(&mut model.spelling, spelling);
                 }
             }
         }
@@ -180,10 +492,20 @@ pub fn key_input(mut model: TypingModel, input: char, timestamp: f64) -> Model {
     } else {
         model.status.last_wrong_keydown = Some(input);
         model.status.unconfirmed.clear();
-        let correctness_segment = &mut model.typing_correctness.lines[current_line_idx].words[model.status.word as usize].segments[model.status.segment as usize];
-        if let Some(c) = correctness_segment.chars.get_mut(model.status.char_ as usize) {
+        let correctness_word = &mut model.typing_correctness.lines[current_line_idx].words[model.status.word as usize];
+        if let Some(c) = correctness_word.segments[model.status.segment as usize].chars.get_mut(model.status.char_ as usize) {
             *c = TypingCorrectnessChar::Incorrect;
         }
+        // 単語を打ち直す場合は、単語の先頭に戻り、正しく入力した文字も未入力に戻す
+        if model.error_policy == ErrorPolicy::Strict {
+            for c in correctness_word.segments.iter_mut().flat_map(|segment| &mut segment.chars) {
+                if *c == TypingCorrectnessChar::Correct {
+                    *c = TypingCorrectnessChar::Pending;
+                }
+            }
+            model.status.segment = 0;
+            model.status.char_ = 0;
+        }
     }
 
     model
@@ -195,6 +517,7 @@ pub fn key_input(mut model: TypingModel, input: char, timestamp: f64) -> Model {
             key: input,
             timestamp,
             is_correct,
+            word: model.status.word,
         });
 
     // 4. セグメント、単語、行、全体の完了チェック
@@ -212,6 +535,28 @@ pub fn key_input(mut model: TypingModel, input: char, timestamp: f64) -> Model {
         }
     }
 
+    // 連続する空白をまとめて進める場合は、続く空白だけの単語も入力済みにする
+    if collapse_following_spaces && !is_finished {
+        let line_idx = model.status.line as usize;
+        while let Some(word) = model.content.lines[line_idx].words.get(model.status.word as usize) {
+            if model.status.segment != 0 || model.status.char_ != 0 || !is_space_word(word) {
+                break;
+            }
+            for segment in &mut model.typing_correctness.lines[line_idx].words[model.status.word as usize].segments {
+                for c in segment.chars.iter_mut().filter(|c| **c == TypingCorrectnessChar::Pending) {
+                    *c = TypingCorrectnessChar::Correct;
+                }
+            }
+            model.status.word += 1;
+            if model.status.word as usize >= model.content.lines[line_idx].words.len() {
+                model.status.word = 0;
+                model.status.line += 1;
+                is_finished = model.status.line as usize >= model.content.lines.len();
+                break;
+            }
+        }
+    }
+
     log(&format!(
         "  [Result] is_correct: {}, is_finished: {}",
         is_correct, is_finished
@@ -221,36 +566,58 @@ pub fn key_input(mut model: TypingModel, input: char, timestamp: f64) -> Model {
         model.status.line, model.status.word, model.status.segment, model.status.char_, model.status.unconfirmed
     ));
 
+    // ミスの回数が上限に達した場合は、その時点でセッションを終える
+    if let ErrorPolicy::SuddenDeath { max_misses } = model.error_policy {
+        if !is_correct && miss_count(&model) >= max_misses as usize {
+            is_finished = true;
+        }
+    }
+
     if is_finished {
-        Model::Result(ResultModel {
-            typing_model: model,
-        })
+        Model::Result(finish_session(model))
     } else {
         Model::Typing(model)
     }
 }
 
+/// セッション全体のミスの回数
+pub fn miss_count(model: &TypingModel) -> usize {
+    all_inputs(model).filter(|input| !input.is_correct).count()
+}
+
+/// 問題の最後まで入力を終えたか。時間制限やミスの上限で途中で終えた場合は `false`
+pub fn is_completed(model: &TypingModel) -> bool {
+    model.status.line as usize >= model.content.lines.len()
+}
+
 pub fn create_typing_correctness_model(content: &Content) -> TypingCorrectnessContent {
-    let mut lines = Vec::new();
-    for line in &content.lines {
-        let mut words = Vec::new();
-        for word in &line.words {
-            let mut segments = Vec::new();
-            for segment in &word.segments {
-                let target_text = match segment {
-                    Segment::Plain { text } => text,
-                    Segment::Annotated { base: _, reading } => reading,
-                };
-                let chars = target_text.chars()
-                    .map(|_| TypingCorrectnessChar::Pending)
-                    .collect();
-                segments.push(TypingCorrectnessSegment { chars });
-            }
-            words.push(TypingCorrectnessWord { segments });
+    TypingCorrectnessContent { lines: content.lines.iter().map(create_typing_correctness_line).collect() }
+}
+
+/// 1行分の、すべての文字が未入力の正誤の記録を作る
+fn create_typing_correctness_line(line: &Line) -> TypingCorrectnessLine {
+    let mut words = Vec::new();
+    for word in &line.words {
+        let mut segments = Vec::new();
+        for segment in &word.segments {
+            let target_text = match segment {
+                Segment::Plain { text } => text,
+                Segment::Annotated { base: _, reading } => reading,
+            };
+            let chars = target_text.chars()
+                .map(|_| TypingCorrectnessChar::Pending)
+                .collect();
+            segments.push(TypingCorrectnessSegment { chars });
         }
-        lines.push(TypingCorrectnessLine { words });
+        words.push(TypingCorrectnessWord { segments });
     }
-    TypingCorrectnessContent { lines }
+    TypingCorrectnessLine { words }
+}
+
+/// 入力中の問題の末尾に行を追加する
+pub fn append_line(model: &mut TypingModel, line: Line) {
+    model.typing_correctness.lines.push(create_typing_correctness_line(&line));
+    model.content.lines.push(line);
 }
 
 impl TypingMetrics {
@@ -261,17 +628,167 @@ impl TypingMetrics {
             total_time: 0.0,
             accuracy: 0.0,
             speed: 0.0,
+            wpm: 0.0,
+            net_wpm: 0.0,
+            corrected_wpm: 0.0,
+            active_time: 0.0,
+            sustained_speed: 0.0,
+            burst_speed: 0.0,
+            reaction_time: None,
+            penalty_time: 0.0,
         }
     }
 
+    /// 一時停止の時間を除いた値に置き換える（速度やWPMも active_time から計算し直す）
+    pub fn excluding_pauses(mut self) -> Self {
+        self.total_time = self.active_time;
+        self.calculate();
+        self
+    }
+
     fn calculate(&mut self) {
         if self.type_count + self.miss_count > 0 {
             self.accuracy = self.type_count as f64 / (self.type_count + self.miss_count) as f64;
         }
         if self.total_time > 0.0 {
             self.speed = (self.type_count as f64) / (self.total_time / 1000.0);
+            // 5打鍵を1語とする一般的なWPMの定義に従う
+            let minutes = self.total_time / 60000.0;
+            let typed_words = (self.type_count + self.miss_count) as f64 / 5.0;
+            self.wpm = typed_words / minutes;
+            self.net_wpm = ((typed_words - self.miss_count as f64) / minutes).max(0.0);
+            self.corrected_wpm = self.type_count as f64 / 5.0 / minutes;
+        }
+        if self.active_time > 0.0 {
+            self.sustained_speed = self.type_count as f64 / (self.active_time / 1000.0);
+        }
+    }
+}
+
+/// 入力を時刻順に並べたもの
+fn all_inputs(model: &TypingModel) -> impl Iterator<Item = &TypingInput> {
+    model.user_input.iter().flat_map(|s| &s.inputs)
+}
+
+/// 打鍵の間隔のうち、一時停止とみなす長さを超えたものを除いて合計した時間
+fn active_time<'a>(inputs: impl Iterator<Item = &'a TypingInput>) -> f64 {
+    let mut total = 0.0;
+    let mut previous: Option<f64> = None;
+    for input in inputs {
+        if let Some(previous) = previous {
+            let gap = input.timestamp - previous;
+            if gap <= SESSION_GAP_MS {
+                total += gap;
+            }
+        }
+        previous = Some(input.timestamp);
+    }
+    total
+}
+
+/// `end` までの直近 `ROLLING_WINDOW_MS` の間の速度（正しい打鍵数/秒）
+///
+/// 最初の入力から `ROLLING_WINDOW_MS` 経っていない場合は、最初の入力からの時間で割る。
+/// 経過時間が1秒未満の場合は値が安定しないため `None` を返す。
+fn rolling_speed(inputs: &[&TypingInput], end: f64) -> Option<f64> {
+    let first = inputs.first()?.timestamp;
+    let window = (end - first).min(ROLLING_WINDOW_MS);
+    if window < 1000.0 {
+        return None;
+    }
+    let count = inputs.iter().filter(|i| i.is_correct && i.timestamp >= end - window && i.timestamp <= end).count();
+    Some(count as f64 / (window / 1000.0))
+}
+
+/// 各入力の時点での直近10秒間の速度のうち、最も速いもの
+///
+/// 毎フレーム計算されるため、窓の始まりを尺取り法で進めて入力数に比例する時間で求める。
+/// 入力の続いた時間が10秒に満たない場合は、最後の入力の時点での速度を返す。
+fn burst_speed(model: &TypingModel) -> f64 {
+    let inputs: Vec<&TypingInput> = all_inputs(model).collect();
+    let Some(first) = inputs.first().map(|i| i.timestamp) else { return 0.0 };
+    let mut best: Option<f64> = None;
+    let mut start = 0;
+    let mut correct_in_window = 0;
+    for end in 0..inputs.len() {
+        if inputs[end].is_correct {
+            correct_in_window += 1;
+        }
+        let window_start = inputs[end].timestamp - ROLLING_WINDOW_MS;
+        while inputs[start].timestamp < window_start {
+            if inputs[start].is_correct {
+                correct_in_window -= 1;
+            }
+            start += 1;
         }
+        if inputs[end].timestamp - first >= ROLLING_WINDOW_MS {
+            let speed = correct_in_window as f64 / (ROLLING_WINDOW_MS / 1000.0);
+            best = Some(best.map_or(speed, |best: f64| best.max(speed)));
+        }
+    }
+    best.unwrap_or_else(|| inputs.last().and_then(|last| rolling_speed(&inputs, last.timestamp)).unwrap_or(0.0))
+}
+
+/// グラフに描く点の最大数
+const TIMELINE_MAX_POINTS: usize = 120;
+
+/// 経過時間ごとの直近10秒間の速度と累計ミス数
+///
+/// 最初の入力からの経過秒数、速度（正しい打鍵数/秒）、その時点までのミス数の組を時刻順に返す。
+/// 点は1秒ごと、長いセッションでは `TIMELINE_MAX_POINTS` 個に収まる間隔で取る。
+pub fn speed_timeline(model: &TypingModel) -> Vec<(f64, f64, usize)> {
+    let inputs: Vec<&TypingInput> = all_inputs(model).collect();
+    let (Some(first), Some(last)) = (inputs.first(), inputs.last()) else { return Vec::new() };
+    let duration = last.timestamp - first.timestamp;
+    let step = (duration / TIMELINE_MAX_POINTS as f64).max(1000.0);
+    let mut points = Vec::new();
+    let mut elapsed: f64 = 0.0;
+    loop {
+        let time = first.timestamp + elapsed.min(duration);
+        let speed = rolling_speed(&inputs, time).unwrap_or(0.0);
+        let misses = inputs.iter().filter(|i| !i.is_correct && i.timestamp <= time).count();
+        points.push((elapsed.min(duration) / 1000.0, speed, misses));
+        if elapsed >= duration {
+            break;
+        }
+        elapsed += step;
     }
+    points
+}
+
+/// 時刻 `now` における直近10秒間の速度（正しい打鍵数/秒）。入力が始まっていない場合などは `None`
+pub fn current_rolling_speed(model: &TypingModel, now: f64) -> Option<f64> {
+    let inputs: Vec<&TypingInput> = all_inputs(model).collect();
+    rolling_speed(&inputs, now)
+}
+
+/// 行ごとの集計。入力のあった行だけを、行番号とともに行の順に返す
+///
+/// セッションは行が変わるときと入力が途切れたときに分かれるため、行の時間はその行の
+/// セッションごとの最初と最後の入力の間隔の合計となり、一時停止の時間は含まない。
+pub fn calculate_line_metrics(model: &TypingModel) -> Vec<(i32, TypingMetrics)> {
+    let mut lines: Vec<(i32, TypingMetrics)> = Vec::new();
+    for session in model.user_input.iter().filter(|s| !s.inputs.is_empty()) {
+        let index = match lines.iter().position(|(line, _)| *line == session.line) {
+            Some(index) => index,
+            None => {
+                lines.push((session.line, TypingMetrics::new()));
+                lines.len() - 1
+            }
+        };
+        let metrics = &mut lines[index].1;
+        metrics.type_count += session.inputs.iter().filter(|i| i.is_correct).count() as i32;
+        metrics.miss_count += session.inputs.iter().filter(|i| !i.is_correct).count() as i32;
+        if let (Some(first), Some(last)) = (session.inputs.first(), session.inputs.last()) {
+            metrics.total_time += last.timestamp - first.timestamp;
+        }
+        metrics.active_time += active_time(session.inputs.iter());
+    }
+    for (_, metrics) in lines.iter_mut() {
+        metrics.calculate();
+    }
+    lines.sort_by_key(|(line, _)| *line);
+    lines
 }
 
 pub fn calculate_total_metrics(model: &TypingModel) -> TypingMetrics {
@@ -310,7 +827,466 @@ pub fn calculate_total_metrics(model: &TypingModel) -> TypingMetrics {
     if last_input_time > first_input_time {
         metrics.total_time = last_input_time - first_input_time;
     }
+    metrics.active_time = active_time(all_inputs(model));
+    metrics.penalty_time = total_miss_count as f64 * model.error_policy.penalty_per_miss();
+    metrics.total_time += metrics.penalty_time;
+    metrics.active_time += metrics.penalty_time;
+
+    metrics.burst_speed = burst_speed(model);
+    metrics.reaction_time = model
+        .start_time
+        .zip(all_inputs(model).next())
+        .map(|(start, first)| (first.timestamp - start).max(0.0));
 
     metrics.calculate();
     metrics
-}
\ No newline at end of file
+}
+
+/// 入力の受け付けが始まるまでの残り時間（ミリ秒）。カウントダウン中でなければ `None`
+pub fn countdown_remaining(model: &TypingModel, now: f64) -> Option<f64> {
+    let start = model.start_time?;
+    (now < start).then_some(start - now)
+}
+
+/// 一時停止していた時間を、セッションの経過時間から除く
+///
+/// 記録した打鍵の時刻と入力の受け付けを始めた時刻を `paused` ミリ秒だけ後にずらし、
+/// 速度や制限時間の残りを、一時停止がなかったものとして求められるようにする。
+pub fn exclude_paused_time(model: &mut TypingModel, paused: f64) {
+    if let Some(start) = model.start_time.as_mut() {
+        *start += paused;
+    }
+    for input in model.user_input.iter_mut().flat_map(|session| &mut session.inputs) {
+        input.timestamp += paused;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::model::{LayoutPreset, Scroll, TimeLimit, TypingStatus};
+    use crate::parser::parse_problem;
+
+    pub(crate) fn new_model_with_layout(problem: &str, layout: Layout) -> TypingModel {
+        let content = parse_problem(problem);
+        let typing_correctness = create_typing_correctness_model(&content);
+        let mode = resolve_input_mode(&content);
+        TypingModel {
+            content,
+            status: TypingStatus { line: 0, word: 0, segment: 0, char_: 0, unconfirmed: Vec::new(), last_wrong_keydown: None },
+            user_input: Vec::new(),
+            typing_correctness,
+            layout,
+            scroll: Scroll { scroll: 0.0, max: 0.0 },
+            mode,
+            spelling: SpellingStats::default(),
+            preferences: SpellingPreferences::default(),
+            start_time: None,
+            time_limit: None,
+            error_policy: ErrorPolicy::default(),
+        }
+    }
+
+    /// 問題文の1行目にキー列を順に入力し、1度もミスせずに最後まで入力できたかを返す
+    fn types_without_miss(line: &str, keys: &str) -> bool {
+        types_without_miss_with_layout(line, keys, Layout::default())
+    }
+
+    fn types_without_miss_with_layout(line: &str, keys: &str, layout: Layout) -> bool {
+        let mut model = new_model_with_layout(&format!("#title test\n{}", line), layout);
+        let key_count = keys.chars().count();
+        for (i, c) in keys.chars().enumerate() {
+            match key_input(model, c, i as f64 * 100.0) {
+                Model::Typing(next) => {
+                    if next.status.last_wrong_keydown.is_some() {
+                        return false;
+                    }
+                    model = next;
+                }
+                Model::Result(result) => {
+                    let all_correct = result.typing_model.user_input.iter().flat_map(|s| &s.inputs).all(|i| i.is_correct);
+                    return all_correct && i + 1 == key_count;
+                }
+            }
+        }
+        false
+    }
+
+    /// キー列を時刻の列と組にして順に入力する。途中でセッションが終わった場合は、その時点の結果を返す
+    pub(crate) fn type_keys(mut model: TypingModel, keys: &str, timestamps: impl IntoIterator<Item = f64>) -> Model {
+        for (c, timestamp) in keys.chars().zip(timestamps) {
+            model = match key_input(model, c, timestamp) {
+                Model::Typing(next) => next,
+                result => return result,
+            };
+        }
+        Model::Typing(model)
+    }
+
+    /// 100ミリ秒ごとの打鍵の時刻
+    pub(crate) fn every_100ms() -> impl Iterator<Item = f64> {
+        (0..).map(|i| i as f64 * 100.0)
+    }
+
+    #[test]
+    fn test_katakana_loanwords_by_romaji() {
+        // 片仮名の読みを、平仮名と同じローマ字で入力できることを確認
+        assert!(types_without_miss("(Destiny/デスティニー)", "desuthini-"));
+        assert!(types_without_miss("コーヒー", "ko-hi-"));
+        assert!(types_without_miss("コンピューター", "konnpyu-ta-"));
+        assert!(types_without_miss("ヴァイオリン", "vaiorinn"));
+        assert!(types_without_miss("カッター", "katta-"));
+        assert!(types_without_miss("パーティー", "pa-texi-"));
+    }
+
+    #[test]
+    fn test_small_ka_ke() {
+        // 小書きの「ヵ」「ヶ」を入力できることを確認
+        assert!(types_without_miss("(一/いっ)-ヵ-(所/しょ)", "ixtuxkasho"));
+        assert!(types_without_miss("ヶ", "lke"));
+        assert!(types_without_miss("ッヶ", "xxke"));
+    }
+
+    #[test]
+    fn test_mixed_script_reading() {
+        // 平仮名と片仮名が混在する読みを入力できることを確認
+        assert!(types_without_miss("(T字路/ティーじろ)", "thi-jiro"));
+        assert!(types_without_miss("(東京/とうきょう)-タワー-に", "toukyoutawa-ni"));
+    }
+
+    #[test]
+    fn test_direct_kana_input_keeps_script() {
+        // かな入力による直接一致は、平仮名と片仮名を区別することを確認
+        assert!(types_without_miss("テスト", "テスト"));
+        assert!(!types_without_miss("テスト", "てすと"));
+        assert!(!types_without_miss("てすと", "テスト"));
+    }
+
+    #[test]
+    fn test_wrong_romaji_for_sokuon_is_rejected() {
+        // 促音を含む片仮名で、促音を落としたローマ字が受け付けられないことを確認
+        assert!(!types_without_miss("ッヂャ", "dya"));
+        assert!(types_without_miss("ッヂャ", "ddya"));
+    }
+
+    #[test]
+    fn test_resolve_input_mode() {
+        // 指定がなければ文字種から判定し、指定があればそれに従うことを確認
+        assert_eq!(resolve_input_mode(&parse_problem("#title t\nHello, \"world\" — 1")), InputMode::Latin);
+        assert_eq!(resolve_input_mode(&parse_problem("#title t\nこんにちは")), InputMode::Japanese);
+        assert_eq!(resolve_input_mode(&parse_problem("#title t\n(Rust/らすと)")), InputMode::Japanese);
+        assert_eq!(resolve_input_mode(&parse_problem("#title t\n#mode japanese\nabc")), InputMode::Japanese);
+        assert_eq!(resolve_input_mode(&parse_problem("#title t\n#mode latin\nこんにちは")), InputMode::Latin);
+    }
+
+    #[test]
+    fn test_latin_mode_is_strict_by_default() {
+        // 英字モードでは大文字・小文字や記号を厳密に照合することを確認
+        assert!(types_without_miss("Hello, World!", "Hello, World!"));
+        assert!(!types_without_miss("Hello", "hello"));
+        assert!(!types_without_miss("a  b", "a b"));
+        assert!(!types_without_miss("“a”", "\"a\""));
+    }
+
+    #[test]
+    fn test_latin_mode_rules() {
+        // 照合規則の指定に従って、大文字・小文字、約物、空白の扱いが緩和されることを確認
+        assert!(types_without_miss("#case ignore\nHello", "hELLO"));
+        assert!(types_without_miss("#punctuation loose\n“don’t” — ok", "\"don't\" - ok"));
+        assert!(types_without_miss("#spacing collapse\na   b", "a b"));
+        assert!(!types_without_miss("#spacing collapse\na   b", "a  b"));
+    }
+
+    #[test]
+    fn test_wpm_metrics() {
+        // 1分間に正しく50打鍵、ミス5回の場合のWPMを確認
+        let mut metrics = TypingMetrics::new();
+        metrics.type_count = 50;
+        metrics.miss_count = 5;
+        metrics.total_time = 60000.0;
+        metrics.calculate();
+        assert!((metrics.wpm - 11.0).abs() < 1e-9);
+        assert!((metrics.net_wpm - 6.0).abs() < 1e-9);
+        assert!((metrics.corrected_wpm - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_jis_kana_layout() {
+        // JISかな配列で、濁点・半濁点を別の打鍵として入力できることを確認
+        let jis = || Layout::from_preset(LayoutPreset::JisKana);
+        assert!(types_without_miss_with_layout("(学校/がっこう)", "か゛っこう", jis()));
+        assert!(types_without_miss_with_layout("(散歩/さんぽ)、ヴァイオリン。", "さんほ゜、う゛ぁいおりん。", jis()));
+        assert!(types_without_miss_with_layout("コーヒー", "こーひー", jis()));
+        // ローマ字は受け付けない
+        assert!(!types_without_miss_with_layout("か", "ka", jis()));
+    }
+
+    #[test]
+    fn test_azik_layout() {
+        // AZIKの拡張入力で、1つの入力から複数のかなを確定できることを確認
+        let azik = || Layout::from_preset(LayoutPreset::Azik);
+        assert!(types_without_miss_with_layout("(漢字/かんじ)", "kzji", azik()));
+        assert!(types_without_miss_with_layout("(高校/こうこう)", "kpkp", azik()));
+        assert!(types_without_miss_with_layout("(洗濯機/せんたっき)", "sdta;ki", azik()));
+        assert!(types_without_miss_with_layout("(先生/せんせい)", "sdsw", azik()));
+        assert!(types_without_miss_with_layout("(本/ほん)", "hoq", azik()));
+        // 通常のローマ字もそのまま使える
+        assert!(types_without_miss_with_layout("(漢字/かんじ)", "kannji", azik()));
+    }
+
+    #[test]
+    fn test_act_layout() {
+        // ACTの拡張入力と、か行の `c` で入力できることを確認
+        let act = || Layout::from_preset(LayoutPreset::Act);
+        assert!(types_without_miss_with_layout("(漢字/かんじ)", "c;ji", act()));
+        assert!(types_without_miss_with_layout("(高校/こうこう)", "c,k,", act()));
+        assert!(types_without_miss_with_layout("(先生/せんせい)", "sjs.", act()));
+        assert!(types_without_miss_with_layout("(春/しゅん)", "syk", act()));
+        assert!(types_without_miss_with_layout("(漢字/かんじ)", "kannji", act()));
+    }
+
+    #[test]
+    fn test_kunrei_and_hepburn_layouts() {
+        // 訓令式・ヘボン式に限定した配列で、もう一方の綴りが受け付けられないことを確認
+        let kunrei = || Layout::from_preset(LayoutPreset::Kunrei);
+        let hepburn = || Layout::from_preset(LayoutPreset::Hepburn);
+        assert!(types_without_miss_with_layout("しゃちょう", "syatyou", kunrei()));
+        assert!(!types_without_miss_with_layout("しゃちょう", "shachou", kunrei()));
+        assert!(types_without_miss_with_layout("しゃちょう", "shachou", hepburn()));
+        assert!(!types_without_miss_with_layout("しゃちょう", "syatyou", hepburn()));
+        assert!(types_without_miss_with_layout("まっちゃ", "maccha", hepburn()));
+        // どちらの方式にも綴りのないかなは、元の綴りで入力できる
+        assert!(types_without_miss_with_layout("ぢ", "di", hepburn()));
+    }
+
+    #[test]
+    fn test_rejected_spelling_stats() {
+        // 許可されていない綴りがミスとして記録され、自然に使う方式が集計されることを確認
+        let model = new_model_with_layout("#title test\nしちつ\n", Layout::from_preset(LayoutPreset::Hepburn));
+        let Model::Result(result) = type_keys(model, "sishichitsu", every_100ms()) else { panic!("session should be completed") };
+        let model = result.typing_model;
+        assert_eq!(model.status.line, 1);
+        assert_eq!(model.spelling, SpellingStats { hepburn: 3, kunrei: 1, rejected: 1 });
+        assert_eq!(preferred_spelling(&model.spelling), Some("Hepburn"));
+
+        // 制限のない配列では、どちらの綴りもミスにならない
+        let model = new_model_with_layout("#title test\nしち\n", Layout::default());
+        let Model::Result(result) = type_keys(model, "siti", every_100ms()) else { panic!("session should be completed") };
+        let model = result.typing_model;
+        assert_eq!(model.spelling, SpellingStats { hepburn: 0, kunrei: 2, rejected: 0 });
+        assert_eq!(preferred_spelling(&model.spelling), Some("Kunrei"));
+    }
+
+    #[test]
+    fn test_single_n_layout_file() {
+        // `@single-n` を指定したキー配列ファイルで、子音の前の n 1文字で「ん」を入力できることを確認
+        let layout = || crate::layout_file::parse_layout_file("@single-n\n", "test").layout;
+        assert!(types_without_miss_with_layout("ほんだ", "honda", layout()));
+        assert!(types_without_miss_with_layout("ほんだ", "honnda", layout()));
+        assert!(types_without_miss_with_layout("こんにちは", "konnnitiha", layout()));
+        assert!(!types_without_miss_with_layout("ほんだ", "honda", Layout::default()));
+    }
+
+    #[test]
+    fn test_spelling_preferences_hint() {
+        // 入力を完了した綴りが記録され、ヒントと最適な打鍵数に使われることを確認
+        let model = new_model_with_layout("#title test\nしゃしん\n", Layout::default());
+        assert_eq!(romaji_hint(&model).as_deref(), Some("syasinn"));
+        let Model::Typing(model) = type_keys(model, "shas", every_100ms()) else { panic!("session should continue") };
+        // 入力途中の綴りと食い違う候補は選ばない
+        assert_eq!(romaji_hint(&model).as_deref(), Some("sinn"));
+        let Model::Result(result) = type_keys(model, "hinn", every_100ms()) else { panic!("session should be completed") };
+        // 効率の計算では、入力した時点までに記録した綴りで打鍵数を数える
+        assert_eq!(result.efficiency.preferred, "shashinn".len());
+        let model = result.typing_model;
+        let preferences = SpellingPreferences::from_text(&model.preferences.to_text());
+        assert_eq!(preferences, model.preferences);
+        assert_eq!(romaji_for_reading("しゃし", &model.layout, &preferences, ""), "shashi");
+        assert_eq!(romaji_for_reading("しゃし", &model.layout, &SpellingPreferences::default(), ""), "syasi");
+        // 英字モードではヒントを出さない
+        assert_eq!(romaji_hint(&new_model_with_layout("#title test\nabc\n", Layout::default())), None);
+    }
+
+    #[test]
+    fn test_keystroke_efficiency() {
+        // 最少の打鍵数が、かなの区切り方を比べて求められることを確認
+        let standard = Layout::default();
+        assert_eq!(minimal_keystrokes_for_reading("がっこう", &standard), 6);
+        assert_eq!(minimal_keystrokes_for_reading("シャカン", &standard), 7);
+        assert_eq!(minimal_keystrokes_for_reading("かん", &Layout::from_preset(LayoutPreset::Azik)), 2);
+
+        // 遠回りの綴り（xtu + ka）で入力すると効率が下がる
+        let model = new_model_with_layout("#title test\nがっこう\n", standard);
+        let keys = "gaxtukou";
+        let Model::Result(result) = type_keys(model, keys, every_100ms()) else { panic!("typing did not finish") };
+        assert_eq!(result.efficiency.minimal, 6);
+        assert_eq!(result.efficiency.actual, keys.len());
+        assert!((result.efficiency.ratio - 6.0 / 8.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_time_based_metrics() {
+        // 行ごとの集計と、一時停止を除いた速度、直近10秒間の速度を確認
+        let model = new_model_with_layout("#title test\nabcd\nef\n", Layout::default());
+        let Model::Result(result) = type_keys(model, "abcdef", [0.0, 200.0, 400.0, 600.0, 5600.0, 5800.0]) else {
+            panic!("session should be completed")
+        };
+        let model = result.typing_model;
+        assert_eq!(model.user_input.iter().map(|s| s.line).collect::<Vec<_>>(), vec![0, 1]);
+
+        let metrics = calculate_total_metrics(&model);
+        assert_eq!(metrics.total_time, 5800.0);
+        assert_eq!(metrics.active_time, 800.0);
+        assert_eq!(metrics.sustained_speed, 7.5);
+        assert!((metrics.burst_speed - 6.0 / 5.8).abs() < 1e-9);
+        assert_eq!(metrics.clone().excluding_pauses().speed, 7.5);
+
+        let lines = calculate_line_metrics(&model);
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].0, lines[0].1.type_count, lines[0].1.total_time), (0, 4, 600.0));
+        assert_eq!((lines[1].0, lines[1].1.type_count, lines[1].1.total_time), (1, 2, 200.0));
+
+        // 直近10秒間の速度は、入力が途切れると下がっていく
+        assert_eq!(current_rolling_speed(&model, 10000.0), Some(0.6));
+        assert_eq!(current_rolling_speed(&model, 20000.0), Some(0.0));
+        assert_eq!(current_rolling_speed(&model, 500.0), None);
+    }
+
+    #[test]
+    fn test_reaction_time() {
+        // カウントダウンの終わりから最初の打鍵までを反応時間とし、入力時間には含めないことを確認
+        let mut model = new_model_with_layout("#title test\nab\n", Layout::default());
+        assert_eq!(calculate_total_metrics(&model).reaction_time, None);
+        model.start_time = Some(3000.0);
+        assert_eq!(countdown_remaining(&model, 1000.0), Some(2000.0));
+        assert_eq!(countdown_remaining(&model, 3000.0), None);
+        let Model::Result(result) = type_keys(model, "ab", [3450.0, 3650.0]) else { panic!("session should be completed") };
+        let metrics = calculate_total_metrics(&result.typing_model);
+        assert_eq!(metrics.reaction_time, Some(450.0));
+        assert_eq!(metrics.total_time, 200.0);
+    }
+
+    #[test]
+    fn test_session_options() {
+        // 開始行・行数・並べ替えが適用され、同じシードなら同じ順序になることを確認
+        let content = parse_problem("#title test\na\nb\nc\nd\ne\nf\n");
+        let first_chars = |content: &Content| -> String { content.lines.iter().map(|line| word_reading(&line.words[0])).collect() };
+
+        let options = SessionOptions { line_count: Some(3), start_line: 2, ..SessionOptions::default() };
+        assert_eq!(first_chars(&apply_session_options(&content, &options, 0)), "bcd");
+        let options = SessionOptions { start_line: 100, ..SessionOptions::default() };
+        assert_eq!(first_chars(&apply_session_options(&content, &options, 0)), "f");
+
+        let options = SessionOptions { shuffle: true, ..SessionOptions::default() };
+        let shuffled = first_chars(&apply_session_options(&content, &options, 42));
+        assert_eq!(shuffled, first_chars(&apply_session_options(&content, &options, 42)));
+        let mut sorted: Vec<char> = shuffled.chars().collect();
+        sorted.sort();
+        assert_eq!(sorted.into_iter().collect::<String>(), "abcdef");
+        let orders: Vec<String> = (0..10).map(|seed| first_chars(&apply_session_options(&content, &options, seed))).collect();
+        assert!(orders.iter().any(|order| order != "abcdef"));
+    }
+
+    #[test]
+    fn test_error_policies() {
+        // ミスの扱いごとに、打ち直す位置・セッションの終わり・罰則の時間が変わることを確認
+        let mut model = new_model_with_layout("#title test\nabc def\n", Layout::default());
+        model.error_policy = ErrorPolicy::Strict;
+        let Model::Typing(model) = type_keys(model, "abx", every_100ms()) else { panic!("session should continue") };
+        assert_eq!((model.status.word, model.status.segment, model.status.char_), (0, 0, 0));
+        let chars = &model.typing_correctness.lines[0].words[0].segments[0].chars;
+        assert_eq!(chars[..3], [TypingCorrectnessChar::Pending, TypingCorrectnessChar::Pending, TypingCorrectnessChar::Incorrect]);
+
+        let mut model = new_model_with_layout("#title test\nabc def\n", Layout::default());
+        model.error_policy = ErrorPolicy::SuddenDeath { max_misses: 2 };
+        let Model::Typing(model) = type_keys(model, "axb", every_100ms()) else { panic!("session should continue") };
+        let Model::Result(result) = type_keys(model, "x", every_100ms()) else { panic!("session should end on the second miss") };
+        assert_eq!(miss_count(&result.typing_model), 2);
+        assert!(!is_completed(&result.typing_model));
+
+        let mut model = new_model_with_layout("#title test\nab\n", Layout::default());
+        model.error_policy = ErrorPolicy::Penalty { seconds: 3 };
+        let Model::Result(result) = type_keys(model, "axb", every_100ms()) else { panic!("session should be completed") };
+        let metrics = calculate_total_metrics(&result.typing_model);
+        assert_eq!(metrics.penalty_time, 3000.0);
+        assert_eq!(metrics.total_time, 3200.0);
+    }
+
+    #[test]
+    fn test_exclude_paused_time() {
+        // 一時停止していた時間が、入力にかかった時間と制限時間の残りに含まれないことを確認
+        let mut model = new_model_with_layout("#title test\nabc\n", Layout::default());
+        model.time_limit = Some(TimeLimit { duration: 60000.0, lap_lines: 1 });
+        model.start_time = Some(0.0);
+        let Model::Typing(mut model) = key_input(model, 'a', 1000.0) else { panic!("session should continue") };
+        exclude_paused_time(&mut model, 5000.0);
+        assert_eq!(model.start_time, Some(5000.0));
+        let Model::Typing(model) = key_input(model, 'b', 6500.0) else { panic!("session should continue") };
+        assert_eq!(time_remaining(&model, 7000.0), Some(58000.0));
+        let Model::Result(result) = key_input(model, 'c', 7000.0) else { panic!("session should be completed") };
+        let metrics = calculate_total_metrics(&result.typing_model);
+        assert_eq!(metrics.total_time, 1000.0);
+        assert_eq!(metrics.reaction_time, Some(1000.0));
+    }
+
+    #[test]
+    fn test_time_limit() {
+        // 問題の行が繰り返され、制限時間で途中の結果が得られることを確認
+        let mut model = new_model_with_layout("#title test\nab\ncd\n", Layout::default());
+        model.content = repeat_content(&model.content, 10);
+        model.typing_correctness = create_typing_correctness_model(&model.content);
+        assert_eq!(model.content.lines.len(), 6);
+        model.time_limit = Some(TimeLimit { duration: 60000.0, lap_lines: 2 });
+        assert_eq!(time_remaining(&model, 0.0), Some(60000.0));
+        model.start_time = Some(1000.0);
+        assert_eq!(time_remaining(&model, 31000.0), Some(30000.0));
+        assert_eq!(time_remaining(&model, 70000.0), Some(0.0));
+
+        let Model::Typing(model) = type_keys(model, "abc", [1200.0, 1400.0, 1600.0]) else {
+            panic!("the session must not finish before the time limit")
+        };
+        // 途中まで入力した単語は効率の計算に含めない
+        let result = finish_session(model);
+        assert_eq!(result.efficiency.minimal, 2);
+        assert_eq!(result.efficiency.actual, 2);
+        assert_eq!(result.efficiency.ratio, 1.0);
+    }
+
+    #[test]
+    fn test_efficiency_on_timeout_mid_word() {
+        // 単語の途中で時間切れになった場合、途中の単語への打鍵を実際の打鍵数に含めないことを確認
+        let mut model = new_model_with_layout("#title test\nかき/くけ\n", Layout::default());
+        model.time_limit = Some(TimeLimit { duration: 1000.0, lap_lines: 1 });
+        model.start_time = Some(0.0);
+        let Model::Typing(model) = type_keys(model, "kakikuk", every_100ms()) else { panic!("session should continue") };
+        assert_eq!(time_remaining(&model, 1000.0), Some(0.0));
+        let result = finish_session(model);
+        assert_eq!(result.efficiency.minimal, 4);
+        assert_eq!(result.efficiency.actual, 4);
+        assert_eq!(result.efficiency.ratio, 1.0);
+    }
+
+    #[test]
+    fn test_speed_timeline() {
+        // 1秒ごとの点が最後の入力まで並び、ミスが累計されることを確認
+        let model = new_model_with_layout("#title test\nabcd\nef\n", Layout::default());
+        let timestamps = [0.0, 100.0, 200.0, 400.0, 600.0, 5600.0, 5800.0];
+        let Model::Result(result) = type_keys(model, "axbcdef", timestamps) else { panic!("session should be completed") };
+        let timeline = speed_timeline(&result.typing_model);
+        let times: Vec<f64> = timeline.iter().map(|point| point.0).collect();
+        assert_eq!(times, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 5.8]);
+        assert_eq!(timeline[0].2, 0);
+        assert_eq!(timeline[1].2, 1);
+        assert_eq!(timeline[1].1, 4.0);
+        assert!((timeline.last().unwrap().1 - 6.0 / 5.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_custom_mapping_text() {
+        // ユーザーのマッピングで、完全に一致する短い入力が優先されることを確認
+        let layout = || Layout::default().with_mapping_text("# 独自の配列\nん n\nです ds dsu\n");
+        assert!(types_without_miss_with_layout("(本/ほん)-です", "honds", layout()));
+        assert!(types_without_miss_with_layout("(本/ほん)-です", "hondesu", layout()));
+        assert!(types_without_miss_with_layout("みかん", "mikan", layout()));
+    }
+}
@@ -3,6 +3,7 @@
 extern crate alloc;
 
 use crate::app::{App, AppEvent, Fonts};
+use crate::layout_data;
 use crate::renderer::{calculate_pixel_font_size, gui_renderer};
 use crate::ui::{self, ActiveLowerElement, LowerTypingSegment, Renderable, UpperSegmentState};
 use ab_glyph::{point, Font, FontRef, OutlinedGlyph, PxScale, ScaleFont};
@@ -72,6 +73,8 @@ pub fn run() -> Status {
                     } else if ch == '\r' {
                         app.on_event(AppEvent::Enter);
                     } else {
+                        // JISかな配列では、英数字をキーに対応するかなに置き換える
+                        let ch = if app.uses_jis_kana_keys() { layout_data::jis_kana_for_ascii(ch).unwrap_or(ch) } else { ch };
                         app.on_event(AppEvent::Char {
                             c: ch,
                             timestamp: now_time,
@@ -123,27 +126,14 @@ pub fn run() -> Status {
                     );
                 }
                 Renderable::Background { gradient } => {
-                    let mut temp_buffer: Vec<u32> = Vec::with_capacity(width * height);
-                    for pixel in pixel_buffer.iter() {
-                        temp_buffer.push(
-                            (pixel.red as u32) << 16
-                                | (pixel.green as u32) << 8
-                                | (pixel.blue as u32),
+                    draw_on_u32_buffer(&mut pixel_buffer, |temp_buffer| {
+                        crate::renderer::draw_linear_gradient(
+                            temp_buffer,
+                            width, height,
+                            gradient.start_color, gradient.end_color,
+                            (0.0, 0.0), (width as f32, height as f32),
                         );
-                    }
-                    crate::renderer::draw_linear_gradient(
-                        &mut temp_buffer,
-                        width, height,
-                        gradient.start_color, gradient.end_color,
-                        (0.0, 0.0), (width as f32, height as f32),
-                    );
-                    for (i, color) in temp_buffer.iter().enumerate() {
-                        pixel_buffer[i] = BltPixel::new(
-                            ((color >> 16) & 0xFF) as u8,
-                            ((color >> 8) & 0xFF) as u8,
-                            (color & 0xFF) as u8,
-                        );
-                    }
+                    });
                 }
                 Renderable::TypingUpper { segments, anchor, shift, align, font_size } => {
                     let pixel_font_size = calculate_pixel_font_size(font_size, width, height);
@@ -164,17 +154,21 @@ pub fn run() -> Status {
                             UpperSegmentState::Active => ui::ACTIVE_COLOR,
                             UpperSegmentState::Pending => ui::PENDING_COLOR,
                         };
-                        draw_text(&mut pixel_buffer, width, current_font, seg.base_text.as_str(), (pen_x as f32, y as f32), pixel_font_size, color);
-                        
-                        if let Some(ruby) = &seg.ruby_text {
-                            let (base_w, ..) = gui_renderer::measure_text(current_font, seg.base_text.as_str(), pixel_font_size);
-                            let (ruby_w, ..) = gui_renderer::measure_text(current_font, ruby.as_str(), ruby_pixel_font_size);
-                            let ruby_x = pen_x as f32 + (base_w as f32 - ruby_w as f32) / 2.0;
-                            let ruby_y = y as f32 - ruby_pixel_font_size*0.5;
-                            draw_text(&mut pixel_buffer, width, current_font, ruby.as_str(), (ruby_x, ruby_y), ruby_pixel_font_size, color);
-                        }
-                        
                         let (seg_width, _, _) = gui_renderer::measure_text(current_font, seg.base_text.as_str(), pixel_font_size);
+                        if seg.hidden {
+                            let (mark_x, mark_y, mark_w, mark_h) = ui::hidden_mark_rect((pen_x, y), seg_width, pixel_font_size);
+                            draw_rect(&mut pixel_buffer, width, mark_x, mark_y, mark_w, mark_h, ui::PENDING_COLOR);
+                        } else {
+                            draw_text(&mut pixel_buffer, width, current_font, seg.base_text.as_str(), (pen_x as f32, y as f32), pixel_font_size, color);
+
+                            if let Some(ruby) = &seg.ruby_text {
+                                let (ruby_w, ..) = gui_renderer::measure_text(current_font, ruby.as_str(), ruby_pixel_font_size);
+                                let ruby_x = pen_x as f32 + (seg_width as f32 - ruby_w as f32) / 2.0;
+                                let ruby_y = y as f32 - ruby_pixel_font_size*0.5;
+                                draw_text(&mut pixel_buffer, width, current_font, ruby.as_str(), (ruby_x, ruby_y), ruby_pixel_font_size, color);
+                            }
+                        }
+
                         pen_x += seg_width as i32;
                     }
                 }
@@ -235,6 +229,17 @@ pub fn run() -> Status {
                         draw_rect(&mut pixel_buffer, width, start_x, start_y, fg_width, bar_height as usize, fg_color);
                     }
                 }
+                Renderable::LineChart { series, x_max, x_label, anchor, shift, align, width_ratio, height_ratio, font_size } => {
+                    let chart_width = (width as f32 * width_ratio) as u32;
+                    let chart_height = (height as f32 * height_ratio) as u32;
+                    let pixel_font_size = calculate_pixel_font_size(font_size, width, height);
+                    let anchor_pos = ui::calculate_anchor_position(anchor, shift, width, height);
+                    let (x, y) = ui::calculate_aligned_position(anchor_pos, chart_width, chart_height, align);
+                    let rect = (x as f32, y as f32, chart_width as f32, chart_height as f32);
+                    draw_on_u32_buffer(&mut pixel_buffer, |temp_buffer| {
+                        gui_renderer::draw_line_chart(temp_buffer, width, current_font, rect, &series, x_max, &x_label, pixel_font_size);
+                    });
+                }
             }
         }
 
@@ -252,6 +257,18 @@ pub fn run() -> Status {
     Status::SUCCESS
 }
 
+/// `u32` のピクセルを扱う共通の描画関数を使うため、一時的な `u32` のバッファに描いて書き戻す
+fn draw_on_u32_buffer(buffer: &mut [BltPixel], draw: impl FnOnce(&mut [u32])) {
+    let mut temp_buffer: Vec<u32> = buffer
+        .iter()
+        .map(|pixel| (pixel.red as u32) << 16 | (pixel.green as u32) << 8 | (pixel.blue as u32))
+        .collect();
+    draw(&mut temp_buffer);
+    for (pixel, color) in buffer.iter_mut().zip(temp_buffer) {
+        *pixel = BltPixel::new(((color >> 16) & 0xFF) as u8, ((color >> 8) & 0xFF) as u8, (color & 0xFF) as u8);
+    }
+}
+
 fn draw_rect(
     buffer: &mut [BltPixel],
     width: usize,
@@ -20,8 +20,8 @@ use alloc::{format, string::{String, ToString}};
 #[cfg(not(feature = "uefi"))]
 use std::string::{String, ToString};
 
-use crate::app::{App, AppState, FontChoice};
-use crate::model::{Segment, TypingCorrectnessChar, TypingCorrectnessSegment, TypingCorrectnessWord};
+use crate::app::{App, AppState, FontChoice, HitTarget, ResultAction, SessionOptionField, SettingsItem, ANALYSIS_VISIBLE_ROWS};
+use crate::model::{ErrorPolicy, InputMode, LayoutPreset, Segment, TypingCorrectnessChar, TypingCorrectnessSegment, TypingCorrectnessWord};
 use crate::renderer::{calculate_pixel_font_size, gui_renderer};
 use crate::typing; // For calculate_total_metrics
 use ab_glyph::FontRef; // FontRefを渡すために必要
@@ -103,6 +103,7 @@ pub struct UpperTypingSegment {
     pub base_text: String,
     pub ruby_text: Option<String>,
     pub state: UpperSegmentState,
+    pub hidden: bool, // 文字を伏せるか。伏せた場合は文字の幅だけ空けて、`hidden_mark_rect` の位置に下線を引く
 }
 
 /// 下段（入力テキスト）のアクティブ（現在入力中）セグメントを構成する要素
@@ -129,6 +130,28 @@ pub enum LowerTypingSegment {
     Active { elements: Vec<ActiveLowerElement> },
 }
 
+/// 折れ線グラフの1系列
+pub struct ChartSeries {
+    pub label: String,
+    pub color: u32,
+    pub points: Vec<(f32, f32)>, // (x, y)。xは0からx_maxまで
+    pub y_max: f32, // 系列ごとの縦軸の上限。縦軸は系列ごとに0からこの値までで描く
+}
+
+impl ChartSeries {
+    /// 点を、グラフの領域に対する0.0〜1.0の比率に変換する
+    pub fn normalized_points(&self, x_max: f32) -> Vec<(f32, f32)> {
+        self.points
+            .iter()
+            .map(|&(x, y)| {
+                let nx = if x_max > 0.0 { (x / x_max).clamp(0.0, 1.0) } else { 0.0 };
+                let ny = if self.y_max > 0.0 { (y / self.y_max).clamp(0.0, 1.0) } else { 0.0 };
+                (nx, ny)
+            })
+            .collect()
+    }
+}
+
 /// 画面に描画すべき要素の種類とレイアウト情報を定義するenum
 pub enum Renderable {
     Background {
@@ -176,13 +199,31 @@ pub enum Renderable {
         bg_color: u32,
         fg_color: u32,
     },
+    /// 軸と目盛りの付いた折れ線グラフ。1つ目の系列の目盛りを左、2つ目の系列の目盛りを右に表示する
+    LineChart {
+        series: Vec<ChartSeries>,
+        x_max: f32,
+        x_label: String,
+        anchor: Anchor,
+        shift: Shift,
+        align: Align,
+        width_ratio: f32, // 画面幅に対する比率
+        height_ratio: f32, // 画面高さに対する比率
+        font_size: FontSize, // 目盛りと凡例のフォントサイズ
+    },
 }
 
+/// グラフの軸と枠の色
+pub const CHART_AXIS_COLOR: u32 = 0xFF_AAAAAA;
+
 #[cfg(target_arch = "wasm32")]
-const MENU_ITEMS: [&str; 2] = ["Start Typing", "Settings"];
+const MENU_ITEMS: [&str; 5] = ["Start Typing", "Review", "Weak Kana Drill", "Endless Words", "Settings"];
 
 #[cfg(not(target_arch = "wasm32"))]
-const MENU_ITEMS: [&str; 3] = ["Start Typing", "Settings", "Quit"];
+const MENU_ITEMS: [&str; 6] = ["Start Typing", "Review", "Weak Kana Drill", "Endless Words", "Settings", "Quit"];
+
+/// 結果画面に効率を並べる、同じ問題の最近のセッションの数
+const RECENT_HISTORY_COUNT: usize = 5;
 
 // --- タイピング画面のレイアウト定数 ---
 pub const BASE_FONT_SIZE_RATIO: f32 = 0.2;
@@ -198,9 +239,57 @@ pub const WRONG_KEY_COLOR: u32 = 0xFF_F55252;
 pub const CURSOR_COLOR: u32 = 0xFF_FFFFFF;
 pub const UNCONFIRMED_COLOR: u32 = 0xFF_CCCCCC;
 
+/// クリックやタップで操作できる領域（ピクセル座標）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HitRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub target: HitTarget,
+}
+
 /// Appの状態を受け取り、描画リスト（UIレイアウト）を構築する
 pub fn build_ui<'a>(app: &App<'a>, font: &FontRef<'a>, width: usize, height: usize) -> Vec<Renderable> {
+    build_frame(app, font, width, height).0
+}
+
+/// 描画リストと一緒に、クリックやタップで操作できる領域の一覧を作る
+///
+/// 領域は項目のテキストを描画する範囲とするため、描画と同じフォントと画面の大きさを渡す。
+pub fn build_hit_regions<'a>(app: &App<'a>, font: &FontRef<'a>, width: usize, height: usize) -> Vec<HitRegion> {
+    let (render_list, hits) = build_frame(app, font, width, height);
+    hits.into_iter()
+        .filter_map(|(index, target)| match &render_list[index] {
+            Renderable::Text { text, anchor, shift, align, font_size, .. } | Renderable::BigText { text, anchor, shift, align, font_size, .. } => {
+                let pixel_font_size = calculate_pixel_font_size(*font_size, width, height);
+                let (text_width, text_height, _) = gui_renderer::measure_text(font, text, pixel_font_size);
+                let anchor_pos = calculate_anchor_position(*anchor, *shift, width, height);
+                let (x, y) = calculate_aligned_position(anchor_pos, text_width, text_height, *align);
+                Some(HitRegion { x, y, width: text_width, height: text_height, target })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// 指定した位置（ピクセル座標）にある項目
+pub fn hit_test(regions: &[HitRegion], x: i32, y: i32) -> Option<HitTarget> {
+    regions
+        .iter()
+        .find(|region| x >= region.x && x < region.x + region.width as i32 && y >= region.y && y < region.y + region.height as i32)
+        .map(|region| region.target)
+}
+
+/// 直前に追加したテキストを、クリックやタップで操作できる項目にする
+fn mark_hit(render_list: &[Renderable], hits: &mut Vec<(usize, HitTarget)>, target: HitTarget) {
+    hits.push((render_list.len() - 1, target));
+}
+
+/// 描画リストと、操作できる項目（描画リストの番号と項目）を作る
+fn build_frame<'a>(app: &App<'a>, font: &FontRef<'a>, width: usize, height: usize) -> (Vec<Renderable>, Vec<(usize, HitTarget)>) {
     let mut render_list = Vec::new();
+    let mut hits = Vec::new();
 
     let menu_gradient = Gradient { start_color: 0xFF_000010, end_color: 0xFF_000000 };
     let typing_gradient = Gradient { start_color: 0xFF_100010, end_color: 0xFF_000000 };
@@ -208,11 +297,13 @@ pub fn build_ui<'a>(app: &App<'a>, font: &FontRef<'a>, width: usize, height: usi
     let settings_gradient = Gradient { start_color: 0xFF_001010, end_color: 0xFF_000000 };
 
     match app.state {
-        AppState::MainMenu => build_main_menu_ui(app, &mut render_list, menu_gradient),
+        AppState::MainMenu => build_main_menu_ui(app, &mut render_list, &mut hits, menu_gradient),
         AppState::Typing => build_typing_ui(app, &mut render_list, typing_gradient, font, width, height),
-        AppState::ProblemSelection => build_problem_selection_ui(app, &mut render_list, menu_gradient),
-        AppState::Result => build_result_ui(app, &mut render_list, result_gradient),
-        AppState::Settings => build_settings_ui(app, &mut render_list, settings_gradient),
+        AppState::ProblemSelection => build_problem_selection_ui(app, &mut render_list, &mut hits, menu_gradient),
+        AppState::Result => build_result_ui(app, &mut render_list, &mut hits, result_gradient),
+        AppState::Settings => build_settings_ui(app, &mut render_list, &mut hits, settings_gradient),
+        AppState::Paused => build_pause_ui(app, &mut render_list, &mut hits, typing_gradient),
+        AppState::Analysis => build_analysis_ui(app, &mut render_list, result_gradient),
     }
 
     if app.state != AppState::Typing {
@@ -272,10 +363,10 @@ pub fn build_ui<'a>(app: &App<'a>, font: &FontRef<'a>, width: usize, height: usi
         color: 0xFF_CCCCCC,
     });
 
-    render_list
+    (render_list, hits)
 }
 
-fn build_main_menu_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gradient) {
+fn build_main_menu_ui(app: &App, render_list: &mut Vec<Renderable>, hits: &mut Vec<(usize, HitTarget)>, gradient: Gradient) {
     render_list.push(Renderable::Background { gradient });
     render_list.push(Renderable::BigText {
         text: "Neknaj Typing MP".to_string(),
@@ -285,24 +376,28 @@ fn build_main_menu_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gr
         font_size: FontSize::WindowHeight(0.20),
         color: 0xFF_FFFFFF,
     });
+    let due_count = app.review_queue.due_items(crate::timestamp::now()).len();
     for (i, item) in MENU_ITEMS.iter().enumerate() {
+        // 復習の項目には、復習の時刻を過ぎた単語の数を添える
+        let item = if *item == "Review" { format!("Review ({} due)", due_count) } else { item.to_string() };
         let (text, color) = if i == app.selected_main_menu_item {
             (format!("> {} <", item), 0xFF_FFFF00)
         } else {
-            (item.to_string(), 0xFF_FFFFFF)
+            (item, 0xFF_FFFFFF)
         };
         render_list.push(Renderable::Text {
             text,
             anchor: Anchor::Center,
-            shift: Shift { x: 0.0, y: 0.0 + (i as f32 * 0.1) },
+            shift: Shift { x: 0.0, y: 0.0 + (i as f32 * 0.07) },
             align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
             font_size: FontSize::WindowHeight(0.05),
             color,
         });
+        mark_hit(render_list, hits, HitTarget::MainMenuItem(i));
     }
 }
 
-fn build_settings_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gradient) {
+fn build_settings_ui(app: &App, render_list: &mut Vec<Renderable>, hits: &mut Vec<(usize, HitTarget)>, gradient: Gradient) {
     render_list.push(Renderable::Background { gradient });
     render_list.push(Renderable::BigText {
         text: "Settings".to_string(),
@@ -313,14 +408,62 @@ fn build_settings_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gra
         color: 0xFF_FFFFFF,
     });
 
-    let fonts = [
-        (FontChoice::YujiSyuku, "Yuji Syuku"),
-        (FontChoice::NotoSerifJP, "Noto Serif JP"),
-    ];
-
-    for (i, (font_choice, name)) in fonts.iter().enumerate() {
+    let items = app.settings_items();
+    // 読み込んだキー配列ファイルが多い場合は、画面に収まるように間隔を詰める
+    let spacing = (0.55 / items.len() as f32).min(0.075);
+    for (i, item) in items.iter().enumerate() {
+        let (name, is_active) = match *item {
+            SettingsItem::Font(font_choice) => (
+                match font_choice {
+                    FontChoice::YujiSyuku => "Font: Yuji Syuku",
+                    FontChoice::NotoSerifJP => "Font: Noto Serif JP",
+                }
+                .to_string(),
+                font_choice == app.font_choice,
+            ),
+            SettingsItem::Layout(layout_choice) => (
+                match layout_choice {
+                    LayoutPreset::Standard => "Layout: Romaji",
+                    LayoutPreset::Azik => "Layout: AZIK",
+                    LayoutPreset::Act => "Layout: ACT",
+                    LayoutPreset::Kunrei => "Layout: Romaji (Kunrei only)",
+                    LayoutPreset::Hepburn => "Layout: Romaji (Hepburn only)",
+                    LayoutPreset::JisKana => "Layout: JIS Kana",
+                }
+                .to_string(),
+                app.custom_layout_choice.is_none() && layout_choice == app.layout_choice,
+            ),
+            SettingsItem::ExcludePauses => ("Speed: Exclude pauses".to_string(), app.exclude_pauses),
+            SettingsItem::Countdown => match app.countdown_seconds {
+                0 => ("Countdown: Off".to_string(), false),
+                seconds => (format!("Countdown: {}s", seconds), true),
+            },
+            SettingsItem::TimeLimit => match app.time_limit_seconds {
+                0 => ("Time limit: Off".to_string(), false),
+                seconds => (format!("Time limit: {}s", seconds), true),
+            },
+            SettingsItem::HideReading => ("Blind: Hide reading".to_string(), app.hide_reading),
+            SettingsItem::HideTyped => ("Blind: Hide typed text".to_string(), app.hide_typed),
+            SettingsItem::Memorize => match app.memorize_seconds {
+                0 => ("Memorize: Off".to_string(), false),
+                seconds => (format!("Memorize: Show each line for {}s", seconds), true),
+            },
+            SettingsItem::ErrorPolicy => match app.error_policy {
+                ErrorPolicy::Continue => ("On error: Continue".to_string(), false),
+                ErrorPolicy::Strict => ("On error: Retype word".to_string(), true),
+                ErrorPolicy::SuddenDeath { max_misses } => (format!("On error: Sudden death ({} misses)", max_misses), true),
+                ErrorPolicy::Penalty { seconds } => (format!("On error: +{}s penalty", seconds), true),
+            },
+            SettingsItem::CustomLayout(index) => {
+                let file = &app.custom_layouts[index];
+                let mut name = format!("Layout: {}", file.name);
+                if !file.issues.is_empty() {
+                    name.push_str(" (!)");
+                }
+                (name, app.custom_layout_choice == Some(index))
+            }
+        };
         let is_selected = i == app.selected_settings_item;
-        let is_active = *font_choice == app.font_choice;
 
         let mut display_text = if is_selected {
             format!("> {}", name)
@@ -337,15 +480,16 @@ fn build_settings_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gra
         render_list.push(Renderable::Text {
             text: display_text,
             anchor: Anchor::Center,
-            shift: Shift { x: 0.0, y: 0.0 + (i as f32 * 0.1) },
+            shift: Shift { x: 0.0, y: -0.1 + (i as f32 * spacing) },
             align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
-            font_size: FontSize::WindowHeight(0.05),
+            font_size: FontSize::WindowHeight((spacing * 0.9).min(0.05)),
             color,
         });
+        mark_hit(render_list, hits, HitTarget::SettingsItem(i));
     }
 }
 
-fn build_problem_selection_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gradient) {
+fn build_problem_selection_ui(app: &App, render_list: &mut Vec<Renderable>, hits: &mut Vec<(usize, HitTarget)>, gradient: Gradient) {
     render_list.push(Renderable::Background { gradient });
     render_list.push(Renderable::BigText {
         text: "Select Problem".to_string(),
@@ -358,7 +502,7 @@ fn build_problem_selection_ui(app: &App, render_list: &mut Vec<Renderable>, grad
 
     let item_height: f32 = 0.06;
     let list_y_start: f32 = 0.4;
-    let list_height: f32 = 0.6;
+    let list_height: f32 = 0.4;
     let items_per_screen = (list_height / item_height).floor() as usize;
 
     let mut start_index = 0;
@@ -384,6 +528,7 @@ fn build_problem_selection_ui(app: &App, render_list: &mut Vec<Renderable>, grad
             font_size: FontSize::WindowHeight(0.045),
             color,
         });
+        mark_hit(render_list, hits, HitTarget::ProblemItem(i));
     }
 
     if start_index > 0 {
@@ -394,6 +539,45 @@ fn build_problem_selection_ui(app: &App, render_list: &mut Vec<Renderable>, grad
         render_list.push(Renderable::Text { text: "▼".to_string(), anchor: Anchor::TopCenter, shift: Shift { x: 0.0, y: list_y_start + list_height },
             align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center }, font_size: FontSize::WindowHeight(0.04), color: 0xFF_AAAAAA });
     }
+
+    // --- 入力する行と順序の設定 ---
+    let options = &app.session_options;
+    let editing = app.editing_option;
+    let start_line = match (editing, options.start_line) {
+        (Some(SessionOptionField::StartLine), 0) => "_".to_string(),
+        (Some(SessionOptionField::StartLine), line) => format!("{}_", line),
+        (_, line) => line.max(1).to_string(),
+    };
+    let seed = match (editing, options.seed) {
+        (Some(SessionOptionField::Seed), Some(seed)) => format!("{}_", seed),
+        (Some(SessionOptionField::Seed), None) => "_".to_string(),
+        (_, Some(seed)) => seed.to_string(),
+        (_, None) => "Auto".to_string(),
+    };
+    let options_text = format!(
+        "Lines: {} | Order: {} | Start line: {} | Seed: {} | Karuta: {}",
+        options.line_count.map_or("All".to_string(), |count| count.to_string()),
+        if options.shuffle { "Random" } else { "In order" },
+        start_line,
+        seed,
+        if options.karuta { "On" } else { "Off" }
+    );
+    render_list.push(Renderable::Text {
+        text: options_text,
+        anchor: Anchor::TopCenter,
+        shift: Shift { x: 0.0, y: list_y_start + list_height + 0.04 },
+        align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+        font_size: FontSize::WindowHeight(0.04),
+        color: if editing.is_some() { 0xFF_FFFF00 } else { 0xFF_FFFFFF },
+    });
+    render_list.push(Renderable::Text {
+        text: "L: Lines  R: Order  K: Karuta  N: Start line  S: Seed (then type digits)".to_string(),
+        anchor: Anchor::TopCenter,
+        shift: Shift { x: 0.0, y: list_y_start + list_height + 0.09 },
+        align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+        font_size: FontSize::WindowHeight(0.03),
+        color: 0xFF_AAAAAA,
+    });
 }
 
 fn is_word_correct(word: &TypingCorrectnessWord) -> bool {
@@ -439,7 +623,13 @@ fn build_typing_ui<'a>(app: &App<'a>, render_list: &mut Vec<Renderable>, gradien
         }).sum::<u32>();
 
         // --- 上段（目標テキスト）の構築 ---
+        // 暗記モードで表示の時間が過ぎた行は、入力を終えた単語だけを表示する
+        let line_hidden = app.is_line_hidden(model, crate::timestamp::now());
+        // かるたの練習では、入力を終えたセグメントとヒントで表示したセグメントだけを表示する
+        let karuta_visible = app.karuta.as_ref().map(|session| session.visible_segments(model));
         let mut upper_segments = Vec::new();
+        let mut segment_idx = 0;
+        let mut cursor_hidden = false; // 入力中のセグメントを伏せているか
         for (word_idx, word) in content_line.words.iter().enumerate() {
             for (seg_idx, seg) in word.segments.iter().enumerate() {
                 let state = if (word_idx as i32) < status.word {
@@ -458,8 +648,14 @@ fn build_typing_ui<'a>(app: &App<'a>, render_list: &mut Vec<Renderable>, gradien
                     Segment::Plain { text } => (text.clone(), None),
                     Segment::Annotated { base, reading } => (base.clone(), Some(reading.clone())),
                 };
-                
-                upper_segments.push(UpperTypingSegment { base_text, ruby_text, state });
+                let ruby_text = ruby_text.filter(|_| !app.hide_reading);
+                let hidden = (line_hidden && (word_idx as i32) >= status.word) || karuta_visible.is_some_and(|visible| segment_idx >= visible);
+                segment_idx += 1;
+                if word_idx as i32 == status.word && seg_idx as i32 == status.segment {
+                    cursor_hidden = hidden;
+                }
+
+                upper_segments.push(UpperTypingSegment { base_text, ruby_text, state, hidden });
             }
         }
         
@@ -534,18 +730,52 @@ fn build_typing_ui<'a>(app: &App<'a>, render_list: &mut Vec<Renderable>, gradien
         }
         
         let lower_y_shift_from_center = (base_pixel_font_size * LOWER_ROW_Y_OFFSET_FACTOR) / height as f32 + 0.01;
-        render_list.push(Renderable::TypingLower {
-            segments: lower_segments,
-            anchor: Anchor::Center,
-            shift: Shift { x: -scroll_offset / width as f32, y: lower_y_shift_from_center },
-            align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
-            font_size: base_font_size,
-            target_line_total_width,
-        });
+        if !app.hide_typed {
+            render_list.push(Renderable::TypingLower {
+                segments: lower_segments,
+                anchor: Anchor::Center,
+                shift: Shift { x: -scroll_offset / width as f32, y: lower_y_shift_from_center },
+                align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+                font_size: base_font_size,
+                target_line_total_width,
+            });
+        }
+
+        // --- ローマ字のヒント（よく使う綴りを優先して表示） ---
+        // 読みを伏せている間は、ヒントも読みを明かすため表示しない
+        if let Some(hint) = typing::romaji_hint(model).filter(|_| !app.hide_reading && !cursor_hidden) {
+            render_list.push(Renderable::Text {
+                text: hint,
+                anchor: Anchor::Center,
+                shift: Shift { x: 0.0, y: 0.32 },
+                align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
+                font_size: FontSize::WindowHeight(0.045),
+                color: 0xFF_888888,
+            });
+        }
 
         // --- コンテキスト行（前後の行）を描画 ---
         let line_count = model.content.lines.len();
-        for &offset in &[-1, 1] {
+        // かるたの練習では、前後の行の代わりに入力中の行の上の句を表示する
+        if let Some(prompt) = app.karuta.as_ref().and_then(|session| session.prompt(model.status.line)) {
+            render_list.push(Renderable::Text {
+                text: prompt.to_string(),
+                anchor: Anchor::Center,
+                shift: Shift { x: 0.0, y: -0.32 },
+                align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
+                font_size: FontSize::WindowHeight(0.08),
+                color: ACTIVE_COLOR,
+            });
+        }
+        // 暗記モードでは、次の行を先に読めないよう前の行だけを表示する
+        let offsets: &[i32] = if app.karuta.is_some() {
+            &[]
+        } else if app.memorize_seconds > 0 {
+            &[-1]
+        } else {
+            &[-1, 1]
+        };
+        for &offset in offsets {
             let line_to_display_signed = model.status.line + offset;
             if line_to_display_signed >= 0 && (line_to_display_signed as usize) < line_count {
                 let line_idx_context = line_to_display_signed as usize;
@@ -561,15 +791,46 @@ fn build_typing_ui<'a>(app: &App<'a>, render_list: &mut Vec<Renderable>, gradien
         }
         
         // --- ステータスパネル ---
-        let metrics = typing::calculate_total_metrics(model);
+        let metrics = app.metrics_for(model);
         let time = metrics.total_time / 1000.0;
-        let status_items = vec![
-            format!("Progress: {} / {}", model.status.line as usize + 1, line_count),
-            format!("Speed: {:.2} KPS", metrics.speed),
+        let now = crate::timestamp::now();
+        let rolling = typing::current_rolling_speed(model, now);
+        let remaining = typing::time_remaining(model, now);
+        let mut status_items = vec![
+            match model.time_limit {
+                // エンドレスモードでは行が増え続けるため、何行目かだけを表示する
+                _ if app.endless.is_some() => format!("Progress: line {}", model.status.line as usize + 1),
+                // 時間制限のある練習では問題の行を繰り返すため、何周目のどの行かを表示する
+                Some(limit) => {
+                    let lap_lines = limit.lap_lines.max(1);
+                    let line = model.status.line as usize;
+                    format!("Progress: Lap {}, {} / {}", line / lap_lines + 1, line % lap_lines + 1, lap_lines)
+                }
+                None => format!("Progress: {} / {}", model.status.line as usize + 1, line_count),
+            },
+            if model.mode == InputMode::Latin {
+                format!("Speed: {:.1} WPM", metrics.wpm)
+            } else {
+                format!("Speed: {:.2} KPS", metrics.speed)
+            },
+            // 直近10秒間の速度（英字モードでは5打鍵を1語としたWPM）
+            match (rolling, model.mode) {
+                (Some(speed), InputMode::Latin) => format!("Last 10s: {:.1} WPM", speed * 12.0),
+                (Some(speed), _) => format!("Last 10s: {:.2} KPS", speed),
+                (None, _) => "Last 10s: -".to_string(),
+            },
             format!("Accuracy: {:.1}%", metrics.accuracy * 100.0),
-            format!("Misses: {}", metrics.miss_count),
+            match model.error_policy {
+                // サドンデスでは、セッションが終わるまでに許されるミスの数も表示する
+                ErrorPolicy::SuddenDeath { max_misses } => format!("Misses: {} / {}", metrics.miss_count, max_misses),
+                _ => format!("Misses: {}", metrics.miss_count),
+            },
             format!("Time: {:02.0}:{:05.2}", (time / 60.0).floor(), time % 60.0),
         ];
+        if let Some(remaining) = remaining {
+            let seconds = remaining / 1000.0;
+            status_items.push(format!("Remaining: {:02.0}:{:04.1}", (seconds / 60.0).floor(), seconds % 60.0));
+        }
         
         let progress_bar_height_ratio = 0.02;
         let status_item_height_ratio = 0.04;
@@ -587,7 +848,13 @@ fn build_typing_ui<'a>(app: &App<'a>, render_list: &mut Vec<Renderable>, gradien
 
         // --- 進捗バー ---
         let char_progress_in_line = model.status.word as f32 / content_line.words.len().max(1) as f32;
-        let detailed_progress_ratio = if line_count > 0 {
+        let detailed_progress_ratio = if let (Some(limit), Some(remaining)) = (model.time_limit, remaining) {
+            // 時間制限のある練習では経過時間の割合を表示する
+            (1.0 - remaining / limit.duration) as f32
+        } else if app.endless.is_some() {
+            // エンドレスモードでは入力中の行の進捗を表示する
+            char_progress_in_line
+        } else if line_count > 0 {
             (model.status.line as f32 + char_progress_in_line) / (line_count as f32)
         } else {
             0.0
@@ -602,10 +869,78 @@ fn build_typing_ui<'a>(app: &App<'a>, render_list: &mut Vec<Renderable>, gradien
             bg_color: 0xFF_555555,
             fg_color: CORRECT_COLOR,
         });
+
+        // --- 開始前のカウントダウン ---
+        if let Some(remaining) = typing::countdown_remaining(model, crate::timestamp::now()) {
+            render_list.push(Renderable::BigText {
+                text: format!("{}", (remaining / 1000.0).ceil() as u32),
+                anchor: Anchor::Center,
+                shift: Shift { x: 0.0, y: -0.2 },
+                align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
+                font_size: FontSize::WindowHeight(0.15),
+                color: 0xFF_FFFF00,
+            });
+        }
     }
 }
 
-fn build_result_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gradient) {
+/// 一時停止画面。入力中の問題は、再開するまで見えないようにする
+fn build_pause_ui(app: &App, render_list: &mut Vec<Renderable>, hits: &mut Vec<(usize, HitTarget)>, gradient: Gradient) {
+    render_list.push(Renderable::Background { gradient });
+    render_list.push(Renderable::BigText {
+        text: "Paused".to_string(),
+        anchor: Anchor::TopCenter,
+        shift: Shift { x: 0.0, y: 0.1 },
+        align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+        font_size: FontSize::WindowHeight(0.2),
+        color: 0xFF_FFFFFF,
+    });
+
+    let Some(model) = &app.typing_model else { return };
+    // 一時停止した時点の経過時間と残り時間を表示する
+    let metrics = app.metrics_for(model);
+    let time = metrics.total_time / 1000.0;
+    let mut summary = format!(
+        "{} | Line {} | Time: {:02.0}:{:05.2}",
+        model.content.title,
+        model.status.line as usize + 1,
+        (time / 60.0).floor(),
+        time % 60.0
+    );
+    if let Some(remaining) = typing::time_remaining(model, app.paused_at.unwrap_or_else(crate::timestamp::now)) {
+        let seconds = remaining / 1000.0;
+        summary.push_str(&format!(" | Remaining: {:02.0}:{:04.1}", (seconds / 60.0).floor(), seconds % 60.0));
+    }
+    render_list.push(Renderable::Text {
+        text: summary,
+        anchor: Anchor::Center,
+        shift: Shift { x: 0.0, y: -0.1 },
+        align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
+        font_size: FontSize::WindowHeight(0.04),
+        color: 0xFF_CCCCCC,
+    });
+
+    // エンドレスモードで入力を始めていれば、中断するとそこまでの結果を表示する
+    let quit = if app.endless.is_some() && !model.user_input.is_empty() { "Finish" } else { "Quit to Menu" };
+    for (i, item) in ["Resume", "Restart", quit].iter().enumerate() {
+        let (text, color) = if i == app.selected_pause_item {
+            (format!("> {} <", item), 0xFF_FFFF00)
+        } else {
+            (item.to_string(), 0xFF_FFFFFF)
+        };
+        render_list.push(Renderable::Text {
+            text,
+            anchor: Anchor::Center,
+            shift: Shift { x: 0.0, y: i as f32 * 0.07 },
+            align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
+            font_size: FontSize::WindowHeight(0.05),
+            color,
+        });
+        mark_hit(render_list, hits, HitTarget::PauseItem(i));
+    }
+}
+
+fn build_result_ui(app: &App, render_list: &mut Vec<Renderable>, hits: &mut Vec<(usize, HitTarget)>, gradient: Gradient) {
     render_list.push(Renderable::Background { gradient });
     render_list.push(Renderable::BigText {
         text: "Result".to_string(),
@@ -616,29 +951,243 @@ fn build_result_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gradi
         color: 0xFF_FFFF00,
     });
 
+    // --- 画面上部の操作ボタン ---
+    let actions = app.result_actions();
+    for (i, action) in actions.iter().enumerate() {
+        let label = match action {
+            ResultAction::Menu => "[Enter] Menu",
+            ResultAction::Retry => "[R] Retry",
+            ResultAction::Next => "[N] Next Problem",
+            ResultAction::Analysis => "[A] Analysis",
+        };
+        render_list.push(Renderable::Text {
+            text: label.to_string(),
+            anchor: Anchor::TopCenter,
+            shift: Shift { x: (i as f32 - (actions.len() - 1) as f32 / 2.0) * 0.2, y: 0.02 },
+            align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+            font_size: FontSize::WindowHeight(0.04),
+            color: 0xFF_CCCCCC,
+        });
+        mark_hit(render_list, hits, HitTarget::ResultAction(*action));
+    }
+
     if let Some(result) = &app.result_model {
-        let metrics = crate::typing::calculate_total_metrics(&result.typing_model);
-        let result_texts = vec![
+        let metrics = app.metrics_for(&result.typing_model);
+        let mut result_texts = vec![
             format!("Typed Chars: {}", metrics.type_count),
             format!("Misses: {}", metrics.miss_count),
             format!("Time: {:.2}s", metrics.total_time / 1000.0),
             format!("Accuracy: {:.2}%", metrics.accuracy * 100.0),
-            format!("Speed: {:.2} chars/sec", metrics.speed),
         ];
+        if app.endless.is_some() {
+            let lines = result.typing_model.status.line as usize;
+            match result.typing_model.time_limit {
+                Some(limit) => result_texts.push(format!("Endless | Time limit: {}s ({} lines done)", limit.duration / 1000.0, lines)),
+                None => result_texts.push(format!("Endless: {} lines done", lines)),
+            }
+        } else if let Some(limit) = result.typing_model.time_limit {
+            let lap_lines = limit.lap_lines.max(1);
+            let lines = result.typing_model.status.line as usize;
+            result_texts.push(format!(
+                "Time limit: {}s | Reached lap {}, line {} / {} ({} lines done)",
+                limit.duration / 1000.0,
+                lines / lap_lines + 1,
+                lines % lap_lines + 1,
+                lap_lines,
+                lines
+            ));
+        }
+        if let Some(seed) = app.session_seed {
+            result_texts.push(format!("Random order seed: {}", seed));
+        }
+        match result.typing_model.error_policy {
+            ErrorPolicy::Continue => {}
+            ErrorPolicy::Strict => result_texts.push("On error: Retype word".to_string()),
+            ErrorPolicy::SuddenDeath { max_misses } => {
+                let model = &result.typing_model;
+                if typing::miss_count(model) < max_misses as usize || typing::is_completed(model) {
+                    result_texts.push(format!("Sudden death: Survived ({} / {} misses)", metrics.miss_count, max_misses));
+                } else {
+                    result_texts.push(format!("Sudden death: Ended at line {} ({} misses)", model.status.line as usize + 1, max_misses));
+                }
+            }
+            ErrorPolicy::Penalty { seconds } => {
+                result_texts.push(format!("Penalty: +{}s per miss ({:.0}s added)", seconds, metrics.penalty_time / 1000.0));
+            }
+        }
+        // かるたの練習では、ヒントを使わずに入力できた割合を速さとは別に示す
+        if let Some(session) = &app.karuta {
+            let (recalled, total) = session.score(&result.typing_model);
+            result_texts.push(format!("Karuta: {} / {} segments from memory | Hints used: {}", recalled, total, session.hints_used()));
+        }
+        // 反応時間はカウントダウンの終わりから最初の打鍵まで。Timeは最初の打鍵から数える
+        if let Some(reaction_time) = metrics.reaction_time {
+            result_texts.push(format!("Reaction: {:.2}s", reaction_time / 1000.0));
+        }
+        if result.typing_model.mode == InputMode::Latin {
+            result_texts.push(format!("Speed: {:.1} WPM", metrics.wpm));
+            result_texts.push(format!("Net: {:.1} WPM | Corrected: {:.1} WPM", metrics.net_wpm, metrics.corrected_wpm));
+        } else {
+            result_texts.push(format!("Speed: {:.2} chars/sec", metrics.speed));
+            let efficiency = &result.efficiency;
+            result_texts.push(format!(
+                "Efficiency: {:.1}% | Keys: {} (min {}, yours {})",
+                efficiency.ratio * 100.0,
+                efficiency.actual,
+                efficiency.minimal,
+                efficiency.preferred
+            ));
+            // 同じ問題の最近のセッション（今回を含む）の効率
+            let recent = crate::history::recent_entries(&app.history, &result.typing_model.content.title.to_string(), RECENT_HISTORY_COUNT);
+            if recent.len() > 1 {
+                let ratios: Vec<String> = recent.iter().map(|entry| format!("{:.0}%", entry.efficiency * 100.0)).collect();
+                result_texts.push(format!("Recent efficiency: {}", ratios.join(", ")));
+            }
+            let spelling = &result.typing_model.spelling;
+            if spelling.hepburn + spelling.kunrei > 0 {
+                let preference = match crate::typing::preferred_spelling(spelling) {
+                    Some(system) => format!(" (prefers {})", system),
+                    None => String::new(),
+                };
+                result_texts.push(format!("Spelling: Hepburn {} | Kunrei {}{}", spelling.hepburn, spelling.kunrei, preference));
+            }
+            if spelling.rejected > 0 {
+                result_texts.push(format!("Disallowed spellings: {}", spelling.rejected));
+            }
+        }
+
+        // 連続して入力した区間の最高速度と、一時停止を除いた平均の速度
+        let (burst, sustained) = (metrics.burst_speed, metrics.sustained_speed);
+        result_texts.push(if result.typing_model.mode == InputMode::Latin {
+            format!("Burst: {:.1} WPM | Sustained: {:.1} WPM", burst * 12.0, sustained * 12.0)
+        } else {
+            format!("Burst: {:.2} | Sustained: {:.2} chars/sec", burst, sustained)
+        });
+        let line_metrics = crate::typing::calculate_line_metrics(&result.typing_model);
+        if let Some((line, slowest)) = line_metrics
+            .iter()
+            .filter(|(_, metrics)| metrics.total_time > 0.0)
+            .min_by(|a, b| a.1.speed.total_cmp(&b.1.speed))
+        {
+            result_texts.push(format!("Slowest line: {} ({:.2} chars/sec)", line + 1, slowest.speed));
+        }
 
+        // --- 速度と累計ミス数の推移 ---
+        let timeline = crate::typing::speed_timeline(&result.typing_model);
+        let x_max = timeline.last().map_or(0.0, |point| point.0) as f32;
+        let is_latin = result.typing_model.mode == InputMode::Latin;
+        // 英字モードでは速度をWPM（5打鍵を1語）で表す
+        let speed_scale = if is_latin { 12.0 } else { 1.0 };
+        let speed_points: Vec<(f32, f32)> = timeline.iter().map(|&(t, speed, _)| (t as f32, (speed * speed_scale) as f32)).collect();
+        let miss_points: Vec<(f32, f32)> = timeline.iter().map(|&(t, _, misses)| (t as f32, misses as f32)).collect();
+        let speed_max = speed_points.iter().map(|p| p.1).fold(0.0, f32::max);
+        let miss_max = miss_points.iter().map(|p| p.1).fold(0.0, f32::max);
+        render_list.push(Renderable::LineChart {
+            series: vec![
+                ChartSeries {
+                    label: if is_latin { "WPM (10s)".to_string() } else { "Speed (10s, chars/sec)".to_string() },
+                    color: CORRECT_COLOR,
+                    points: speed_points,
+                    y_max: if speed_max > 0.0 { speed_max } else { 1.0 },
+                },
+                ChartSeries {
+                    label: "Misses".to_string(),
+                    color: INCORRECT_COLOR,
+                    points: miss_points,
+                    y_max: miss_max.max(1.0),
+                },
+            ],
+            x_max,
+            x_label: "Time (s)".to_string(),
+            anchor: Anchor::Center,
+            shift: Shift { x: 0.0, y: -0.08 },
+            align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
+            width_ratio: 0.8,
+            height_ratio: 0.26,
+            font_size: FontSize::WindowHeight(0.03),
+        });
+
+        // 項目が多い場合は、画面に収まるように間隔を詰める
+        let spacing = (0.38 / result_texts.len() as f32).min(0.05);
         for (i, text) in result_texts.iter().enumerate() {
             render_list.push(Renderable::Text {
                 text: text.clone(),
                 anchor: Anchor::Center,
-                shift: Shift { x: 0.0, y: -0.1 + (i as f32 * 0.08) },
+                shift: Shift { x: 0.0, y: 0.1 + (i as f32 * spacing) },
                 align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Center },
-                font_size: FontSize::WindowHeight(0.05),
+                font_size: FontSize::WindowHeight((spacing * 0.9).min(0.04)),
                 color: 0xFF_FFFFFF,
             });
         }
     }
 }
 
+/// 分析画面。行ごとの速度・正確さ・ミスの回数を一覧にする
+fn build_analysis_ui(app: &App, render_list: &mut Vec<Renderable>, gradient: Gradient) {
+    render_list.push(Renderable::Background { gradient });
+    render_list.push(Renderable::BigText {
+        text: "Analysis".to_string(),
+        anchor: Anchor::TopCenter,
+        shift: Shift { x: 0.0, y: 0.03 },
+        align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+        font_size: FontSize::WindowHeight(0.12),
+        color: 0xFF_FFFF00,
+    });
+
+    let Some(result) = &app.result_model else { return };
+    let model = &result.typing_model;
+    let line_metrics = typing::calculate_line_metrics(model);
+    let slowest = line_metrics
+        .iter()
+        .filter(|(_, metrics)| metrics.total_time > 0.0)
+        .min_by(|a, b| a.1.speed.total_cmp(&b.1.speed))
+        .map(|(line, _)| *line);
+
+    let first = app.analysis_scroll.min(line_metrics.len());
+    let last = (first + ANALYSIS_VISIBLE_ROWS).min(line_metrics.len());
+    render_list.push(Renderable::Text {
+        text: format!("Lines {}-{} of {}", first + 1, last, line_metrics.len()),
+        anchor: Anchor::TopCenter,
+        shift: Shift { x: 0.0, y: 0.17 },
+        align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+        font_size: FontSize::WindowHeight(0.04),
+        color: 0xFF_CCCCCC,
+    });
+
+    for (i, (line, metrics)) in line_metrics[first..last].iter().enumerate() {
+        // 行の内容は先頭の数文字だけを表示する
+        let text = model.content.lines.get(*line as usize).map(|line| line.to_string()).unwrap_or_default();
+        let mut excerpt: String = text.chars().take(12).collect();
+        if text.chars().count() > 12 {
+            excerpt.push('…');
+        }
+        let speed = if model.mode == InputMode::Latin {
+            format!("{:.1} WPM", metrics.wpm)
+        } else {
+            format!("{:.2} chars/sec", metrics.speed)
+        };
+        render_list.push(Renderable::Text {
+            text: format!("{}. {} | {} | {:.1}% | Misses: {}", line + 1, excerpt, speed, metrics.accuracy * 100.0, metrics.miss_count),
+            anchor: Anchor::TopCenter,
+            shift: Shift { x: 0.0, y: 0.24 + i as f32 * 0.06 },
+            align: Align { horizontal: HorizontalAlign::Center, vertical: VerticalAlign::Top },
+            font_size: FontSize::WindowHeight(0.045),
+            color: if Some(*line) == slowest { INCORRECT_COLOR } else { 0xFF_FFFFFF },
+        });
+    }
+}
+
+/// 伏せたセグメントの代わりに描く下線の矩形(x, y, 幅, 高さ)を求める
+///
+/// `pen` はセグメントの描画開始座標（左上）、`segment_width` は伏せた文字の幅。
+pub fn hidden_mark_rect(pen: (i32, i32), segment_width: u32, pixel_font_size: f32) -> (usize, usize, usize, usize) {
+    let inset = (segment_width as f32 * 0.05) as i32;
+    let x = (pen.0 + inset).max(0) as usize;
+    let y = (pen.1 as f32 + pixel_font_size * 0.9).max(0.0) as usize;
+    let w = (segment_width as i32 - inset * 2).max(1) as usize;
+    let h = (pixel_font_size * 0.05).max(1.0) as usize;
+    (x, y, w, h)
+}
 
 /// AnchorとShiftから、基準となる座標(x, y)を計算する
 pub fn calculate_anchor_position(
@@ -1,6 +1,7 @@
 // src/wasm.rs
 
 use crate::app::{App, AppEvent, Fonts};
+use crate::layout_data;
 use crate::renderer::{calculate_pixel_font_size, gui_renderer};
 use crate::ui::{self, ActiveLowerElement, LowerTypingSegment, Renderable, UpperSegmentState};
 use ab_glyph::FontRef;
@@ -9,7 +10,7 @@ use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::Clamped;
 use wasm_bindgen::JsCast;
-use web_sys::{CanvasRenderingContext2d, HtmlInputElement, ImageData, InputEvent, KeyboardEvent};
+use web_sys::{Blob, BlobPropertyBag, CanvasRenderingContext2d, HtmlAnchorElement, HtmlInputElement, ImageData, InputEvent, KeyboardEvent, MouseEvent, TouchEvent, Url, WheelEvent};
 
 thread_local! {
     static APP_INSTANCE: RefCell<Option<Rc<RefCell<App<'static>>>>> = RefCell::new(None);
@@ -105,16 +106,65 @@ pub fn start() -> Result<(), JsValue> {
     let size = Rc::new(RefCell::new((0, 0)));
     let last_time = Rc::new(RefCell::new(0.0));
 
-    // canvasクリックでinput要素にフォーカスを当てるリスナー
+    // canvasクリックで、クリックした項目をAppに知らせ、input要素にフォーカスを当てるリスナー
     {
+        let app_clone = app.clone();
+        let size_clone = size.clone();
         let input_clone = input_element.clone();
-        let closure = Closure::<dyn FnMut()>::new(move || {
+        let closure = Closure::<dyn FnMut(_)>::new(move |event: MouseEvent| {
+            click_at(&mut app_clone.borrow_mut(), *size_clone.borrow(), event.offset_x(), event.offset_y());
             let _ = input_clone.focus();
         });
         canvas.add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())?;
         closure.forget();
     }
 
+    // タッチのタップ。後に続くクリックのイベントを打ち消し、同じ項目を2回選ばないようにする
+    {
+        let app_clone = app.clone();
+        let size_clone = size.clone();
+        let canvas_clone = canvas.clone();
+        let input_clone = input_element.clone();
+        let closure = Closure::<dyn FnMut(_)>::new(move |event: TouchEvent| {
+            let touches = event.changed_touches();
+            if touches.length() != 1 {
+                return;
+            }
+            let Some(touch) = touches.get(0) else { return };
+            event.prevent_default();
+            let rect = canvas_clone.get_bounding_client_rect();
+            let x = (touch.client_x() as f64 - rect.left()) as i32;
+            let y = (touch.client_y() as f64 - rect.top()) as i32;
+            click_at(&mut app_clone.borrow_mut(), *size_clone.borrow(), x, y);
+            let _ = input_clone.focus();
+        });
+        canvas.add_event_listener_with_callback("touchend", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+
+    // マウスホイールで問題の一覧などをスクロールする
+    {
+        let app_clone = app.clone();
+        let closure = Closure::<dyn FnMut(_)>::new(move |event: WheelEvent| {
+            if event.delta_y() != 0.0 {
+                event.prevent_default();
+                app_clone.borrow_mut().on_event(AppEvent::Scroll { delta: if event.delta_y() > 0.0 { 1 } else { -1 } });
+            }
+        });
+        canvas.add_event_listener_with_callback("wheel", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+
+    // タブの切り替えなどでページからフォーカスが外れたことをAppに知らせる
+    {
+        let app_clone = app.clone();
+        let closure = Closure::<dyn FnMut()>::new(move || {
+            app_clone.borrow_mut().on_event(AppEvent::FocusLost);
+        });
+        window.add_event_listener_with_callback("blur", closure.as_ref().unchecked_ref())?;
+        closure.forget();
+    }
+
     // ウィンドウリサイズ時の処理
     {
         let canvas_clone = canvas.clone();
@@ -166,7 +216,17 @@ pub fn start() -> Result<(), JsValue> {
                     event.prevent_default();
                     app.on_event(AppEvent::Escape)
                 },
-                _ => {}
+                _ => {
+                    // JISかな配列では、キーの位置（code）に対応するかなを直接入力する
+                    // keydownを打ち消すことで、input要素への入力イベントは発生しない
+                    if app.uses_jis_kana_keys() && !event.is_composing() {
+                        let kana = jis_key_for_code(&event.code()).and_then(|key| layout_data::jis_kana_for_key(key, event.shift_key()));
+                        if let Some(kana) = kana {
+                            event.prevent_default();
+                            app.on_event(AppEvent::Char { c: kana, timestamp: crate::timestamp::now() });
+                        }
+                    }
+                }
             }
         });
         document.add_event_listener_with_callback("keydown", closure.as_ref().unchecked_ref())?;
@@ -263,17 +323,21 @@ pub fn start() -> Result<(), JsValue> {
                                 UpperSegmentState::Active => ui::ACTIVE_COLOR,
                                 UpperSegmentState::Pending => ui::PENDING_COLOR,
                             };
-                            gui_renderer::draw_text(&mut pixel_buffer, width, current_font, &seg.base_text, (pen_x as f32, y as f32), pixel_font_size, color);
-                            
-                            if let Some(ruby) = &seg.ruby_text {
-                                let (base_w, ..) = gui_renderer::measure_text(current_font, &seg.base_text, pixel_font_size);
-                                let (ruby_w, ..) = gui_renderer::measure_text(current_font, ruby, ruby_pixel_font_size);
-                                let ruby_x = pen_x as f32 + (base_w as f32 - ruby_w as f32) / 2.0;
-                                let ruby_y = y as f32 - ruby_pixel_font_size*0.5;
-                                gui_renderer::draw_text(&mut pixel_buffer, width, current_font, ruby, (ruby_x, ruby_y), ruby_pixel_font_size, color);
-                            }
-                            
                             let (seg_width, _, _) = gui_renderer::measure_text(current_font, &seg.base_text, pixel_font_size);
+                            if seg.hidden {
+                                let (mark_x, mark_y, mark_w, mark_h) = ui::hidden_mark_rect((pen_x, y), seg_width, pixel_font_size);
+                                gui_renderer::draw_rect(&mut pixel_buffer, width, mark_x, mark_y, mark_w, mark_h, ui::PENDING_COLOR);
+                            } else {
+                                gui_renderer::draw_text(&mut pixel_buffer, width, current_font, &seg.base_text, (pen_x as f32, y as f32), pixel_font_size, color);
+
+                                if let Some(ruby) = &seg.ruby_text {
+                                    let (ruby_w, ..) = gui_renderer::measure_text(current_font, ruby, ruby_pixel_font_size);
+                                    let ruby_x = pen_x as f32 + (seg_width as f32 - ruby_w as f32) / 2.0;
+                                    let ruby_y = y as f32 - ruby_pixel_font_size*0.5;
+                                    gui_renderer::draw_text(&mut pixel_buffer, width, current_font, ruby, (ruby_x, ruby_y), ruby_pixel_font_size, color);
+                                }
+                            }
+
                             pen_x += seg_width as i32;
                         }
                     }
@@ -334,6 +398,15 @@ pub fn start() -> Result<(), JsValue> {
                             gui_renderer::draw_rect(&mut pixel_buffer, width, start_x, start_y, fg_width, bar_height as usize, fg_color);
                         }
                     }
+                    Renderable::LineChart { series, x_max, x_label, anchor, shift, align, width_ratio, height_ratio, font_size } => {
+                        let chart_width = (width as f32 * width_ratio) as u32;
+                        let chart_height = (height as f32 * height_ratio) as u32;
+                        let pixel_font_size = calculate_pixel_font_size(font_size, width, height);
+                        let anchor_pos = ui::calculate_anchor_position(anchor, shift, width, height);
+                        let (x, y) = ui::calculate_aligned_position(anchor_pos, chart_width, chart_height, align);
+                        let rect = (x as f32, y as f32, chart_width as f32, chart_height as f32);
+                        gui_renderer::draw_line_chart(&mut pixel_buffer, width, current_font, rect, &series, x_max, &x_label, pixel_font_size);
+                    }
                  }
             }
             
@@ -363,6 +436,11 @@ pub fn start() -> Result<(), JsValue> {
             app_borrow_mut.should_reset_ime = false;
         }
 
+        // --- 結果のHTML書き出し処理 ---
+        app_borrow_mut.flush_pending_export(|file_name, html| {
+            download_text(html, file_name, "text/html").map_err(|_| "the download could not be started".to_string())
+        });
+
         request_animation_frame(f.borrow().as_ref().unwrap());
     }));
     request_animation_frame(g.borrow().as_ref().unwrap());
@@ -370,6 +448,55 @@ pub fn start() -> Result<(), JsValue> {
     Ok(())
 }
 
+/// canvas上の位置（ピクセル）にある項目を選んだことをAppに知らせる
+fn click_at(app: &mut App<'static>, (width, height): (usize, usize), x: i32, y: i32) {
+    let regions = ui::build_hit_regions(app, app.get_current_font(), width, height);
+    if let Some(target) = ui::hit_test(&regions, x, y) {
+        app.on_event(AppEvent::Click { target });
+    }
+}
+
+/// KeyboardEventのcode（物理的なキーの位置）を、JISキーボードの英数モードで刻印されている文字に変換する
+fn jis_key_for_code(code: &str) -> Option<char> {
+    if let Some(letter) = code.strip_prefix("Key") {
+        return letter.chars().next().map(|c| c.to_ascii_lowercase());
+    }
+    if let Some(digit) = code.strip_prefix("Digit") {
+        return digit.chars().next();
+    }
+    match code {
+        "Minus" => Some('-'),
+        "Equal" => Some('^'),
+        "IntlYen" => Some('¥'),
+        "BracketLeft" => Some('@'),
+        "BracketRight" => Some('['),
+        "Semicolon" => Some(';'),
+        "Quote" => Some(':'),
+        "Backslash" => Some(']'),
+        "Comma" => Some(','),
+        "Period" => Some('.'),
+        "Slash" => Some('/'),
+        "IntlRo" => Some('\\'),
+        _ => None,
+    }
+}
+
+/// テキストをBlobにして、ファイルとしてダウンロードさせる
+fn download_text(text: &str, file_name: &str, mime_type: &str) -> Result<(), JsValue> {
+    let parts = js_sys::Array::of1(&JsValue::from_str(text));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = Blob::new_with_str_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let document = web_sys::window().unwrap().document().unwrap();
+    let anchor = document.create_element("a")?.dyn_into::<HtmlAnchorElement>()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    Url::revoke_object_url(&url)
+}
+
 fn request_animation_frame(f: &Closure<dyn FnMut()>) {
     web_sys::window()
         .unwrap()